@@ -0,0 +1,425 @@
+use std::cmp::Reverse;
+use std::collections::{HashMap, VecDeque};
+use std::fs;
+use std::path::Path;
+
+use bevy::audio::AudioSink;
+use bevy::prelude::*;
+use rand::Rng;
+use serde::Deserialize;
+
+use crate::blaster::OverheatEvent;
+use crate::civilian::CivilianLostEvent;
+use crate::components::{GameUi, Health, HudText, Player};
+use crate::constants::*;
+use crate::pause::not_paused;
+use crate::projectile_collision::EnemyKilledEvent;
+use crate::resources::GameFont;
+use crate::settings::Settings;
+use crate::states::GameState;
+use crate::utils::CooldownTimer;
+
+//Qwark's running commentary. Lines come from assets/quips.ron by trigger and show as a subtitle above
+//the heat gauge, with a voice clip when there is one. Only one line is up at a time, so priorities,
+//cooldowns and a gap between lines decide what actually gets said
+pub struct AnnouncerPlugin;
+
+impl Plugin for AnnouncerPlugin {
+    fn build(&self, app: &mut App) {
+        app.add_event::<QuipEvent>()
+            .add_startup_system(load_quips)
+            .add_system_set(
+                SystemSet::on_enter(GameState::MainGame)
+                    .with_system(spawn_subtitle_system)
+                    .with_system(reset_announcer_system),
+            )
+            .add_system_set(
+                SystemSet::on_update(GameState::MainGame)
+                    .with_system(kill_quips_system)
+                    .with_system(overheat_quips_system)
+                    .with_system(low_health_quips_system)
+                    .with_system(civilian_lost_quips_system),
+            )
+            .add_system_set(
+                SystemSet::on_enter(GameState::Intermission).with_system(round_clear_quips_system),
+            )
+            .add_system_set(
+                SystemSet::on_exit(GameState::MainGame).with_system(silence_announcer_system),
+            )
+            //Lines carry on over the intermission screen, they just hold still while paused
+            .add_system(speak_system.with_run_criteria(not_paused).label(SpeakLabel))
+            .add_system(
+                subtitle_system
+                    .with_run_criteria(not_paused)
+                    .after(SpeakLabel),
+            );
+    }
+}
+
+#[derive(SystemLabel, Debug, Clone, PartialEq, Eq, Hash)]
+struct SpeakLabel;
+
+const QUIPS_PATH: &str = "assets/quips.ron";
+
+const SPEAKER_COLOR: Color = Color::rgb(1.0, 0.8, 0.0);
+const SUBTITLE_COLOR: Color = Color::rgb(0.95, 0.95, 0.95);
+
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Hash, Deserialize)]
+pub enum QuipTrigger {
+    FirstKill,
+    Multikill,
+    Overheat,
+    LowHealth,
+    CivilianLost,
+    RoundClear,
+}
+
+pub struct QuipEvent {
+    pub trigger: QuipTrigger,
+}
+
+#[derive(Deserialize)]
+struct LineDescriptor {
+    text: String,
+    #[serde(default)]
+    audio: Option<String>,
+}
+
+#[derive(Deserialize)]
+struct QuipDescriptor {
+    priority: u32,
+    #[serde(default)]
+    cooldown_seconds: f32,
+    lines: Vec<LineDescriptor>,
+}
+
+struct Line {
+    text: String,
+    audio: Option<Handle<AudioSource>>,
+}
+
+struct QuipSet {
+    priority: u32,
+    cooldown_seconds: f32,
+    lines: Vec<Line>,
+}
+
+struct Quips(HashMap<QuipTrigger, QuipSet>);
+
+struct Speaking {
+    timer: Timer,
+    sink: Option<Handle<AudioSink>>,
+}
+
+struct Announcer {
+    current: Option<Speaking>,
+    //Quiet time after a line before the next one, unless it outranks the last one
+    gap: CooldownTimer,
+    last_priority: u32,
+    cooldowns: HashMap<QuipTrigger, CooldownTimer>,
+    //Lines used lately by trigger, oldest first
+    recent: HashMap<QuipTrigger, VecDeque<usize>>,
+    //Subtitle text waiting for subtitle_system to put it up
+    pending_text: Option<String>,
+}
+
+impl Default for Announcer {
+    fn default() -> Self {
+        Self {
+            current: None,
+            gap: CooldownTimer::from_seconds(QUIP_GAP_SECONDS),
+            last_priority: 0,
+            cooldowns: HashMap::new(),
+            recent: HashMap::new(),
+            pending_text: None,
+        }
+    }
+}
+
+impl Announcer {
+    fn pick_line(&mut self, trigger: QuipTrigger, count: usize) -> usize {
+        let recent = self.recent.entry(trigger).or_default();
+        let fresh: Vec<usize> = (0..count).filter(|line| !recent.contains(line)).collect();
+        let line = fresh[rand::thread_rng().gen_range(0..fresh.len())];
+        recent.push_back(line);
+        //Half of them are held back so nothing comes round again too soon, a lone line just repeats
+        while recent.len() > count / 2 {
+            recent.pop_front();
+        }
+        line
+    }
+
+    fn stop(&mut self, audio_sinks: &Assets<AudioSink>) {
+        let sink = self.current.take().and_then(|speaking| speaking.sink);
+        if let Some(sink) = sink.and_then(|sink| audio_sinks.get(&sink)) {
+            sink.stop();
+        }
+    }
+}
+
+//Tracks what the quips need to know across frames
+#[derive(Default)]
+struct QuipTracker {
+    first_kill: bool,
+    kill_times: VecDeque<f64>,
+    low_health: bool,
+}
+
+#[derive(Component)]
+struct Subtitle;
+
+//A broken quips file is a bug in the assets, but a voice clip that hasn't been recorded yet isn't
+fn load_quips(mut commands: Commands, asset_server: Res<AssetServer>) {
+    let contents = fs::read_to_string(QUIPS_PATH)
+        .unwrap_or_else(|err| panic!("Couldn't read {}: {}", QUIPS_PATH, err));
+    let descriptors: HashMap<QuipTrigger, QuipDescriptor> = ron::from_str(&contents)
+        .unwrap_or_else(|err| panic!("Couldn't load {}: {}", QUIPS_PATH, err));
+
+    let quips = descriptors
+        .into_iter()
+        .filter(|(_, descriptor)| !descriptor.lines.is_empty())
+        .map(|(trigger, descriptor)| {
+            let lines = descriptor
+                .lines
+                .into_iter()
+                .map(|line| {
+                    let audio = line
+                        .audio
+                        .filter(|file| Path::new("assets").join(file).exists())
+                        .map(|file| asset_server.load(file.as_str()));
+                    Line {
+                        text: line.text,
+                        audio,
+                    }
+                })
+                .collect();
+            let quip_set = QuipSet {
+                priority: descriptor.priority,
+                cooldown_seconds: descriptor.cooldown_seconds,
+                lines,
+            };
+            (trigger, quip_set)
+        })
+        .collect();
+    commands.insert_resource(Quips(quips));
+    commands.insert_resource(Announcer::default());
+    commands.insert_resource(QuipTracker::default());
+}
+
+fn spawn_subtitle_system(mut cmds: Commands, font: Res<GameFont>) {
+    let text_style = |color| TextStyle {
+        font: font.0.clone(),
+        font_size: 24.0,
+        color,
+    };
+    cmds.spawn_bundle(NodeBundle {
+        style: Style {
+            position_type: PositionType::Absolute,
+            position: UiRect {
+                left: Val::Percent(0.0),
+                top: Val::Percent(75.0),
+                ..default()
+            },
+            size: Size::new(Val::Percent(100.0), Val::Auto),
+            justify_content: JustifyContent::Center,
+            ..default()
+        },
+        color: Color::NONE.into(),
+        ..default()
+    })
+    .insert(GameUi)
+    .with_children(|parent| {
+        parent
+            .spawn_bundle(TextBundle::from_sections([
+                TextSection::new("", text_style(SPEAKER_COLOR)),
+                TextSection::new("", text_style(SUBTITLE_COLOR)),
+            ]))
+            .insert(Subtitle)
+            .insert(HudText { base_size: 24.0 });
+    });
+}
+
+fn reset_announcer_system(mut announcer: ResMut<Announcer>, mut tracker: ResMut<QuipTracker>) {
+    *announcer = Announcer::default();
+    *tracker = QuipTracker::default();
+}
+
+fn silence_announcer_system(audio_sinks: Res<Assets<AudioSink>>, mut announcer: ResMut<Announcer>) {
+    announcer.stop(&audio_sinks);
+}
+
+fn kill_quips_system(
+    time: Res<Time>,
+    mut tracker: ResMut<QuipTracker>,
+    mut killed_events: EventReader<EnemyKilledEvent>,
+    mut send_quip: EventWriter<QuipEvent>,
+) {
+    let now = time.seconds_since_startup();
+    for _ in killed_events.iter() {
+        if !tracker.first_kill {
+            tracker.first_kill = true;
+            send_quip.send(QuipEvent {
+                trigger: QuipTrigger::FirstKill,
+            });
+        }
+        tracker.kill_times.push_back(now);
+    }
+
+    while tracker
+        .kill_times
+        .front()
+        .is_some_and(|kill| now - kill > MULTIKILL_WINDOW_SECONDS as f64)
+    {
+        tracker.kill_times.pop_front();
+    }
+    if tracker.kill_times.len() >= MULTIKILL_KILLS {
+        tracker.kill_times.clear();
+        send_quip.send(QuipEvent {
+            trigger: QuipTrigger::Multikill,
+        });
+    }
+}
+
+fn overheat_quips_system(
+    mut overheat_events: EventReader<OverheatEvent>,
+    mut send_quip: EventWriter<QuipEvent>,
+) {
+    if overheat_events.iter().count() > 0 {
+        send_quip.send(QuipEvent {
+            trigger: QuipTrigger::Overheat,
+        });
+    }
+}
+
+//Once on the way down into low health, not again until the player's healed back out of it
+fn low_health_quips_system(
+    mut tracker: ResMut<QuipTracker>,
+    query: Query<&Health, (With<Player>, Changed<Health>)>,
+    mut send_quip: EventWriter<QuipEvent>,
+) {
+    for health in query.iter() {
+        let low = health.health > 0
+            && (health.health as f32) <= PLAYER_HEALTH as f32 * LOW_HEALTH_FRACTION;
+        if low && !tracker.low_health {
+            send_quip.send(QuipEvent {
+                trigger: QuipTrigger::LowHealth,
+            });
+        }
+        tracker.low_health = low;
+    }
+}
+
+fn civilian_lost_quips_system(
+    mut lost_events: EventReader<CivilianLostEvent>,
+    mut send_quip: EventWriter<QuipEvent>,
+) {
+    if lost_events.iter().next().is_some() {
+        send_quip.send(QuipEvent {
+            trigger: QuipTrigger::CivilianLost,
+        });
+    }
+}
+
+fn round_clear_quips_system(mut send_quip: EventWriter<QuipEvent>) {
+    send_quip.send(QuipEvent {
+        trigger: QuipTrigger::RoundClear,
+    });
+}
+
+fn speak_system(
+    time: Res<Time>,
+    audio: Res<Audio>,
+    audio_sinks: Res<Assets<AudioSink>>,
+    settings: Res<Settings>,
+    quips: Res<Quips>,
+    mut announcer: ResMut<Announcer>,
+    mut quip_events: EventReader<QuipEvent>,
+) {
+    announcer.gap.tick(time.delta());
+    for cooldown in announcer.cooldowns.values_mut() {
+        cooldown.tick(time.delta());
+    }
+
+    //When several go off at once the most important one gets the first go
+    let mut triggers: Vec<(QuipTrigger, &QuipSet)> = quip_events
+        .iter()
+        .filter_map(|event| {
+            quips
+                .0
+                .get(&event.trigger)
+                .map(|quip_set| (event.trigger, quip_set))
+        })
+        .collect();
+    triggers.sort_by_key(|(_, quip_set)| Reverse(quip_set.priority));
+
+    for (trigger, quip_set) in triggers {
+        if announcer
+            .cooldowns
+            .get(&trigger)
+            .is_some_and(|cooldown| !cooldown.ready())
+        {
+            continue;
+        }
+        let outranks = quip_set.priority > announcer.last_priority;
+        let quiet = announcer.current.is_none() && announcer.gap.ready();
+        if !quiet && !outranks {
+            continue;
+        }
+
+        announcer.stop(&audio_sinks);
+        let line = &quip_set.lines[announcer.pick_line(trigger, quip_set.lines.len())];
+        let sink = line.audio.as_ref().map(|clip| {
+            let sink = audio.play_with_settings(
+                clip.clone(),
+                PlaybackSettings::ONCE.with_volume(settings.sfx_volume()),
+            );
+            audio_sinks.get_handle(sink)
+        });
+        let seconds =
+            QUIP_BASE_SECONDS + line.text.chars().count() as f32 * QUIP_SECONDS_PER_CHARACTER;
+        announcer.current = Some(Speaking {
+            timer: Timer::from_seconds(seconds, false),
+            sink,
+        });
+        announcer.last_priority = quip_set.priority;
+        announcer.pending_text = Some(line.text.clone());
+
+        let mut cooldown = CooldownTimer::from_seconds(quip_set.cooldown_seconds);
+        cooldown.trigger();
+        announcer.cooldowns.insert(trigger, cooldown);
+    }
+}
+
+fn subtitle_system(
+    time: Res<Time>,
+    mut announcer: ResMut<Announcer>,
+    mut query: Query<&mut Text, With<Subtitle>>,
+) {
+    let pending_text = announcer.pending_text.take();
+    let finished = match announcer.current.as_mut() {
+        Some(speaking) => speaking.timer.tick(time.delta()).finished(),
+        None => false,
+    };
+    if finished {
+        //The line's done, the gap keeps anything of the same priority or lower quiet for a bit
+        announcer.current = None;
+        announcer.gap.trigger();
+    }
+    let fade = announcer.current.as_ref().map_or(0.0, |speaking| {
+        let remaining = speaking.timer.duration() - speaking.timer.elapsed();
+        (remaining.as_secs_f32() / QUIP_FADE_SECONDS).min(1.0)
+    });
+
+    for mut text in query.iter_mut() {
+        if let Some(line) = pending_text.as_ref() {
+            text.sections[0].value = "Qwark: ".to_string();
+            text.sections[1].value = line.clone();
+        }
+        if announcer.current.is_none() {
+            text.sections[0].value.clear();
+            text.sections[1].value.clear();
+        }
+        text.sections[0].style.color = *SPEAKER_COLOR.clone().set_a(fade);
+        text.sections[1].style.color = *SUBTITLE_COLOR.clone().set_a(fade);
+    }
+}
@@ -0,0 +1,307 @@
+use std::collections::VecDeque;
+
+use bevy::prelude::*;
+
+use crate::blaster::OverheatEvent;
+use crate::components::{Civilian, GameUi, HudText};
+use crate::constants::*;
+use crate::projectile_collision::LivingBeingHitEvent;
+use crate::resources::GameFont;
+use crate::rounds::RoundTracker;
+use crate::spawn_manager::NewRoundEvent;
+use crate::states::GameState;
+use crate::utils::CooldownTimer;
+
+//Big centred banners that slide across the screen, used for the round intro and for mid-round warnings.
+//Anything can send an AnnouncementEvent, they're queued up and shown one at a time so they never overlap
+pub struct BannerPlugin;
+
+impl Plugin for BannerPlugin {
+    fn build(&self, app: &mut App) {
+        app.add_event::<AnnouncementEvent>()
+            .insert_resource(BannerQueue::default())
+            .insert_resource(CivilianAlertCooldown(CooldownTimer::from_seconds(
+                CIVILIAN_ALERT_COOLDOWN_SECONDS,
+            )))
+            .add_system_set(
+                SystemSet::on_enter(GameState::MainGame).with_system(spawn_banner_system),
+            )
+            .add_system_set(
+                SystemSet::on_update(GameState::MainGame)
+                    .with_system(round_intro_system)
+                    .with_system(civilian_alert_system)
+                    .with_system(overheat_alert_system)
+                    .with_system(queue_announcements_system.label(QueueAnnouncementsLabel))
+                    .with_system(banner_system.after(QueueAnnouncementsLabel)),
+            )
+            .add_system_set(
+                SystemSet::on_exit(GameState::MainGame).with_system(clear_banner_queue),
+            );
+    }
+}
+
+#[derive(SystemLabel, Debug, Clone, PartialEq, Eq, Hash)]
+struct QueueAnnouncementsLabel;
+
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum AnnouncementKind {
+    RoundIntro,
+    FinalRound,
+    Warning,
+}
+
+impl AnnouncementKind {
+    fn color(&self) -> Color {
+        match self {
+            AnnouncementKind::RoundIntro => Color::rgb(0.9, 0.9, 0.9),
+            AnnouncementKind::FinalRound => Color::rgb(1.0, 0.8, 0.0),
+            AnnouncementKind::Warning => Color::rgb(1.0, 0.1, 0.0),
+        }
+    }
+
+    //How long the banner sits still in the middle, not counting sliding in and out
+    fn hold_seconds(&self) -> f32 {
+        match self {
+            AnnouncementKind::Warning => WARNING_BANNER_HOLD_SECONDS,
+            _ => ROUND_BANNER_HOLD_SECONDS,
+        }
+    }
+}
+
+#[derive(Clone, Debug)]
+pub struct AnnouncementEvent {
+    pub title: String,
+    pub subtitle: Option<String>,
+    pub kind: AnnouncementKind,
+}
+
+#[derive(Default)]
+struct BannerQueue {
+    pending: VecDeque<AnnouncementEvent>,
+    current: Option<(AnnouncementEvent, Timer)>,
+}
+
+impl BannerQueue {
+    //A warning that's already showing or waiting doesn't need to be queued again
+    fn contains(&self, title: &str) -> bool {
+        self.current
+            .as_ref()
+            .is_some_and(|(current, _)| current.title == title)
+            || self.pending.iter().any(|pending| pending.title == title)
+    }
+}
+
+//Civilians get hit in bursts, so only shout about it every few seconds
+struct CivilianAlertCooldown(CooldownTimer);
+
+#[derive(Component)]
+struct Banner;
+
+#[derive(Component)]
+struct BannerTitle;
+
+#[derive(Component)]
+struct BannerSubtitle;
+
+fn spawn_banner_system(mut cmds: Commands, font: Res<GameFont>) {
+    let text_style = |font_size| TextStyle {
+        font: font.0.clone(),
+        font_size,
+        color: Color::NONE,
+    };
+    cmds.spawn_bundle(NodeBundle {
+        style: Style {
+            position_type: PositionType::Absolute,
+            position: UiRect {
+                left: Val::Percent(-100.0),
+                top: Val::Percent(20.0),
+                ..default()
+            },
+            size: Size::new(Val::Percent(100.0), Val::Auto),
+            flex_direction: FlexDirection::ColumnReverse,
+            align_items: AlignItems::Center,
+            padding: UiRect::all(Val::Percent(1.0)),
+            ..default()
+        },
+        color: Color::NONE.into(),
+        ..default()
+    })
+    .insert(Banner)
+    .insert(GameUi)
+    .with_children(|parent| {
+        parent
+            .spawn_bundle(TextBundle::from_section("", text_style(64.0)))
+            .insert(BannerTitle)
+            .insert(HudText { base_size: 64.0 });
+        parent
+            .spawn_bundle(TextBundle::from_section("", text_style(28.0)))
+            .insert(BannerSubtitle)
+            .insert(HudText { base_size: 28.0 });
+    });
+}
+
+//"Defeat 6 enemies - protect 2 civilians"
+fn round_objective(round_tracker: &RoundTracker) -> Option<String> {
+    let round_data = round_tracker.current_round_data()?;
+    let enemies = round_data.number_of_crabs
+        + round_data.number_of_bots
+        + round_data.number_of_tanks
+        + round_data.number_of_exploders;
+    let mut objective = format!(
+        "Defeat {} enem{}",
+        enemies,
+        if enemies == 1 { "y" } else { "ies" }
+    );
+    if round_data.number_of_civilians > 0 {
+        objective.push_str(&format!(
+            " - protect {} civilian{}",
+            round_data.number_of_civilians,
+            if round_data.number_of_civilians == 1 {
+                ""
+            } else {
+                "s"
+            }
+        ));
+    }
+    Some(objective)
+}
+
+fn round_intro_system(
+    mut new_round_events: EventReader<NewRoundEvent>,
+    round_tracker: Res<RoundTracker>,
+    mut send_announcement: EventWriter<AnnouncementEvent>,
+) {
+    for event in new_round_events.iter() {
+        let (title, kind) = if round_tracker.is_endless() {
+            (
+                format!("Wave {}", event.round),
+                AnnouncementKind::RoundIntro,
+            )
+        } else if round_tracker.is_final_round() {
+            ("Final Round".to_string(), AnnouncementKind::FinalRound)
+        } else {
+            (
+                format!("Round {}", event.round),
+                AnnouncementKind::RoundIntro,
+            )
+        };
+        send_announcement.send(AnnouncementEvent {
+            title,
+            subtitle: round_objective(&round_tracker),
+            kind,
+        });
+    }
+}
+
+fn civilian_alert_system(
+    time: Res<Time>,
+    mut cooldown: ResMut<CivilianAlertCooldown>,
+    mut hit_events: EventReader<LivingBeingHitEvent>,
+    civilian_query: Query<(), With<Civilian>>,
+    mut send_announcement: EventWriter<AnnouncementEvent>,
+) {
+    cooldown.0.tick(time.delta());
+    let civilian_hit = hit_events
+        .iter()
+        .any(|event| civilian_query.contains(event.entity));
+    if civilian_hit && cooldown.0.ready() {
+        cooldown.0.trigger();
+        send_announcement.send(AnnouncementEvent {
+            title: "Civilians under attack!".to_string(),
+            subtitle: None,
+            kind: AnnouncementKind::Warning,
+        });
+    }
+}
+
+fn overheat_alert_system(
+    mut overheat_events: EventReader<OverheatEvent>,
+    mut send_announcement: EventWriter<AnnouncementEvent>,
+) {
+    if overheat_events.iter().last().is_some() {
+        send_announcement.send(AnnouncementEvent {
+            title: "Blaster overheated!".to_string(),
+            subtitle: None,
+            kind: AnnouncementKind::Warning,
+        });
+    }
+}
+
+fn queue_announcements_system(
+    mut announcements: EventReader<AnnouncementEvent>,
+    mut queue: ResMut<BannerQueue>,
+) {
+    for announcement in announcements.iter() {
+        if announcement.kind != AnnouncementKind::Warning || !queue.contains(&announcement.title) {
+            queue.pending.push_back(announcement.clone());
+        }
+    }
+}
+
+//Slides in from the left, holds, then slides out to the right while fading
+fn banner_system(
+    time: Res<Time>,
+    mut queue: ResMut<BannerQueue>,
+    mut banner_query: Query<(&mut Style, &mut UiColor), With<Banner>>,
+    mut title_query: Query<&mut Text, (With<BannerTitle>, Without<BannerSubtitle>)>,
+    mut subtitle_query: Query<&mut Text, (With<BannerSubtitle>, Without<BannerTitle>)>,
+) {
+    let (mut style, mut color) = match banner_query.get_single_mut() {
+        Ok(banner) => banner,
+        Err(_) => return,
+    };
+
+    if queue.current.is_none() {
+        if let Some(next) = queue.pending.pop_front() {
+            let seconds = next.kind.hold_seconds() + BANNER_SLIDE_SECONDS * 2.0;
+            for mut text in title_query.iter_mut() {
+                text.sections[0].value = next.title.clone();
+            }
+            for mut text in subtitle_query.iter_mut() {
+                text.sections[0].value = next.subtitle.clone().unwrap_or_default();
+            }
+            queue.current = Some((next, Timer::from_seconds(seconds, false)));
+        }
+    }
+
+    let finished = match queue.current.as_mut() {
+        Some((announcement, timer)) => {
+            timer.tick(time.delta());
+            let elapsed = timer.elapsed_secs();
+            let remaining = timer.duration().as_secs_f32() - elapsed;
+            let (left, alpha) = if elapsed < BANNER_SLIDE_SECONDS {
+                let t = elapsed / BANNER_SLIDE_SECONDS;
+                (-100.0 * (1.0 - t).powi(2), t)
+            } else if remaining < BANNER_SLIDE_SECONDS {
+                let t = 1.0 - remaining / BANNER_SLIDE_SECONDS;
+                (100.0 * t * t, 1.0 - t)
+            } else {
+                (0.0, 1.0)
+            };
+
+            style.position.left = Val::Percent(left);
+            *color = Color::rgba(0.0, 0.0, 0.0, 0.5 * alpha).into();
+            for mut text in title_query.iter_mut() {
+                text.sections[0].style.color = *announcement.kind.color().set_a(alpha);
+            }
+            for mut text in subtitle_query.iter_mut() {
+                text.sections[0].style.color = *Color::rgb(0.9, 0.9, 0.9).set_a(alpha);
+            }
+            timer.finished()
+        }
+        None => false,
+    };
+
+    //Visibility isn't inherited by the text, so empty it out instead of hiding the banner
+    if finished {
+        queue.current = None;
+        *color = Color::NONE.into();
+        for mut text in title_query.iter_mut().chain(subtitle_query.iter_mut()) {
+            text.sections[0].value.clear();
+        }
+    }
+}
+
+fn clear_banner_queue(mut queue: ResMut<BannerQueue>) {
+    *queue = BannerQueue::default();
+}
@@ -1,12 +1,21 @@
+use std::collections::HashMap;
+use std::fs;
+
 use bevy::prelude::*;
 use bevy::utils::tracing::span::AsId;
 use bevy_rapier2d::prelude::*;
 use bevy_rapier2d::rapier::prelude::CollisionEventFlags;
 use bevy_rapier2d::{prelude::*, rapier::prelude::Translation};
 use nalgebra::MatrixSliceMut1x3;
+use serde::Deserialize;
 
-use crate::components::{AreaOfEffect, Blaster, FromEnemy, FromPlayer, Health, Lives, LivingBeing};
-use crate::constants::{BLASTER_GROUP, BLASTER_SPEED};
+use crate::components::{
+    AreaOfEffect, Background, Blaster, FromEnemy, FromPlayer, Health, Lives, LivingBeing,
+};
+use crate::constants::{BLASTER_DAMAGE, BLASTER_GROUP, BLASTER_SPEED, POWER_SHOT_DAMAGE};
+use crate::fixed_step::{FixedStepApp, FixedSystems};
+use crate::graphics::{Animator, SpriteSheets};
+use crate::particles::EffectEvent;
 use crate::player;
 use crate::projectile_collision::LivingBeingHitEvent;
 use crate::states::GameState;
@@ -17,52 +26,129 @@ pub struct BlasterFiredEvent {
     pub from_player: bool,
     pub memberships: u32,
     pub filter: u32,
-    pub color: Color,
+    pub projectile: &'static str,
+    pub power_shot: bool,
+}
+
+pub struct OverheatEvent {
+    pub position: Vec2,
 }
 
 pub struct BlasterPlugin;
 
 impl Plugin for BlasterPlugin {
     fn build(&self, app: &mut App) {
-        app.add_system_set(
-            SystemSet::on_update(GameState::MainGame)
-                .with_system(on_blaster_fired)
-                .with_system(destroy_blaster_on_contact)
-                .with_system(damage_on_contact),
-        );
+        app.add_startup_system(load_projectile_looks)
+            .add_fixed_systems(
+                FixedSystems::on_update(GameState::MainGame)
+                    .with_system(on_blaster_fired)
+                    .with_system(destroy_blaster_on_contact)
+                    .with_system(damage_on_contact),
+            );
     }
 }
 
+const PROJECTILES_PATH: &str = "assets/projectiles.ron";
+
+#[derive(Deserialize)]
+pub struct ProjectileLook {
+    sheet: String,
+    size: (f32, f32),
+    color: (f32, f32, f32, f32),
+    #[serde(default)]
+    trail: Option<String>,
+}
+
+#[derive(Deserialize)]
+struct WeaponLooks {
+    shot: ProjectileLook,
+    //Falls back to the normal shot for weapons that can't power up
+    #[serde(default)]
+    power_shot: Option<ProjectileLook>,
+}
+
+//Weapon looks by the projectile name in WeaponData
+pub struct ProjectileLooks(HashMap<String, WeaponLooks>);
+
+impl ProjectileLooks {
+    pub fn get(&self, projectile: &str, power_shot: bool) -> &ProjectileLook {
+        let looks = self.0.get(projectile).unwrap_or_else(|| {
+            panic!(
+                "No projectile called {} in {}",
+                projectile, PROJECTILES_PATH
+            )
+        });
+        match &looks.power_shot {
+            Some(power_look) if power_shot => power_look,
+            _ => &looks.shot,
+        }
+    }
+}
+
+fn load_projectile_looks(mut commands: Commands) {
+    let contents = fs::read_to_string(PROJECTILES_PATH)
+        .unwrap_or_else(|err| panic!("Couldn't read {}: {}", PROJECTILES_PATH, err));
+    let looks = ron::from_str(&contents)
+        .unwrap_or_else(|err| panic!("Couldn't load {}: {}", PROJECTILES_PATH, err));
+    commands.insert_resource(ProjectileLooks(looks));
+}
+
 pub fn on_blaster_fired(
     mut commands: Commands,
     mut bullet_fired_events: EventReader<BlasterFiredEvent>,
+    looks: Res<ProjectileLooks>,
+    sprite_sheets: Res<SpriteSheets>,
+    mut send_effect: EventWriter<EffectEvent>,
 ) {
     for event in bullet_fired_events.iter() {
-        insert_blaster_at(&mut commands, event)
+        let look = looks.get(event.projectile, event.power_shot);
+        let blaster = insert_blaster_at(&mut commands, event, look, &sprite_sheets);
+        if let Some(trail) = &look.trail {
+            send_effect.send(EffectEvent {
+                name: trail.clone(),
+                position: event.position,
+                direction: -event.direction,
+                follow: Some(blaster),
+            });
+        }
     }
 }
 
-pub fn insert_blaster_at(cmds: &mut Commands, options: &BlasterFiredEvent) {
+//Shots fly in a straight line, so they're turned to face their direction of travel once when spawned
+pub fn insert_blaster_at(
+    cmds: &mut Commands,
+    options: &BlasterFiredEvent,
+    look: &ProjectileLook,
+    sprite_sheets: &SpriteSheets,
+) -> Entity {
     let speed = options.direction.normalize() * BLASTER_SPEED;
+    let damage = if options.power_shot {
+        POWER_SHOT_DAMAGE
+    } else {
+        BLASTER_DAMAGE
+    };
+    let sheet = sprite_sheets.get(&look.sheet);
+    let (r, g, b, a) = look.color;
 
     cmds.spawn()
-        .insert_bundle(SpriteBundle {
-            sprite: Sprite {
-                color: options.color,
-                custom_size: Some(Vec2::new(10.0, 10.0)),
+        .insert_bundle(SpriteSheetBundle {
+            sprite: TextureAtlasSprite {
+                color: Color::rgba(r, g, b, a),
+                custom_size: Some(Vec2::new(look.size.0, look.size.1)),
                 ..Default::default()
             },
+            texture_atlas: sheet.atlas.clone(),
             ..Default::default()
         })
+        .insert(Animator::new(sheet))
         //Rigid Body
         .insert(RigidBody::KinematicVelocityBased)
         .insert(LockedAxes::ROTATION_LOCKED)
         .insert(Velocity::linear(speed))
-        .insert_bundle(TransformBundle::from(Transform::from_xyz(
-            options.position.x,
-            options.position.y,
-            0.0,
-        )))
+        .insert_bundle(TransformBundle::from(
+            Transform::from_xyz(options.position.x, options.position.y, 0.0)
+                .with_rotation(Quat::from_rotation_z(speed.y.atan2(speed.x))),
+        ))
         //Collider
         .insert(ActiveEvents::COLLISION_EVENTS)
         .insert(ActiveCollisionTypes::all())
@@ -73,7 +159,12 @@ pub fn insert_blaster_at(cmds: &mut Commands, options: &BlasterFiredEvent) {
             (options.filter),
         ))
         //Custom Functionality
-        .insert(Blaster { damage: 1 });
+        .insert(Blaster {
+            damage,
+            power_shot: options.power_shot,
+        })
+        .insert(Background)
+        .id()
 }
 
 pub fn destroy_blaster_on_contact(
@@ -117,7 +208,7 @@ pub fn destroy_blaster_on_contact(
 pub fn damage_on_contact(
     mut commands: Commands,
     mut send_living_being_hit: EventWriter<LivingBeingHitEvent>,
-    blasters: Query<(Entity, &Blaster)>,
+    blasters: Query<(Entity, &Blaster, &Transform)>,
     mut living_being: Query<(Entity), With<LivingBeing>>,
     mut contact_events: EventReader<CollisionEvent>,
 ) {
@@ -128,7 +219,7 @@ pub fn damage_on_contact(
                 let second = *second;
 
                 if flags == &CollisionEventFlags::empty() {
-                    for (blaster_entity, blaster) in blasters.iter() {
+                    for (blaster_entity, blaster, blaster_tf) in blasters.iter() {
                         for (being) in living_being.iter() {
                             if ((first == blaster_entity && second == being)
                                 || (first == being && second == blaster_entity))
@@ -136,6 +227,8 @@ pub fn damage_on_contact(
                                 send_living_being_hit.send(LivingBeingHitEvent {
                                     entity: being,
                                     damage: blaster.damage,
+                                    power_shot: blaster.power_shot,
+                                    position: blaster_tf.translation.truncate(),
                                 });
                             }
                         }
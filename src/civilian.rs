@@ -1,42 +1,55 @@
+use std::sync::Arc;
+
 use bevy::ecs::event::Event;
 use bevy::prelude::*;
 use bevy_rapier2d::prelude::*;
 use bevy_rapier2d::rapier::prelude::CollisionEventFlags;
 use rand::Rng;
 
-use crate::components::{AnimationTimer, Civilian, LivingBeing, Player};
+use crate::components::{Civilian, Dead, Lives, LivingBeing, Player};
 use crate::constants::{
     CIVILIAN_GROUP, PLAYER_HEIGHT, PLAYER_SPEED, PLAYER_SPRITE_SCALE, PLAYER_WIDTH,
 };
+use crate::fixed_step::{FixedStepApp, FixedSystems};
+use crate::graphics::{Animator, SpriteSheet, SpriteSheets};
+use crate::player::LifeLostEvent;
 use crate::projectile_collision::LivingBeingHitEvent;
-use crate::resources::{PlayerScore, WindowSize};
-use crate::states::{CivilianAnimationInfo, CivilianState, GameState};
+use crate::resources::{GameRng, WindowSize};
+use crate::states::GameState;
 
 pub struct CivilianPlugin;
 
+pub struct CivilianRescuedEvent {
+    pub position: Vec2,
+}
+
+//A civilian still waiting to be rescued when the player's last life runs out
+pub struct CivilianLostEvent {
+    pub position: Vec2,
+}
+
 impl Plugin for CivilianPlugin {
     fn build(&self, app: &mut App) {
-        app.add_system_set(
-            SystemSet::on_update(GameState::MainGame)
-                .with_system(civilian_ai_system)
-                .with_system(civilian_despawn_system)
-                .with_system(civilian_state_system),
-        );
+        app.add_fixed_event::<CivilianRescuedEvent>()
+            .add_fixed_event::<CivilianLostEvent>()
+            .add_fixed_systems(
+                FixedSystems::on_update(GameState::MainGame)
+                    .with_system(civilian_ai_system)
+                    .with_system(civilian_despawn_system)
+                    .with_system(civilian_lost_system)
+                    .with_system(civilian_state_system),
+            );
     }
 }
 
-pub fn spawn_civilian(
-    cmds: &mut Commands,
-    position: Vec2,
-    texture_atlas_handle: &Handle<TextureAtlas>,
-) {
+pub fn spawn_civilian(cmds: &mut Commands, position: Vec2, sheet: &Arc<SpriteSheet>) {
     let transform = Transform {
         translation: Vec3::new(position.x, position.y, 0.0),
         scale: Vec3::splat(PLAYER_SPRITE_SCALE),
         ..default()
     };
     let sprite = SpriteSheetBundle {
-        texture_atlas: texture_atlas_handle.clone(),
+        texture_atlas: sheet.atlas.clone(),
         transform: transform,
 
         ..default()
@@ -54,30 +67,26 @@ pub fn spawn_civilian(
         .insert(ActiveEvents::COLLISION_EVENTS)
         .insert(CollisionGroups::new(CIVILIAN_GROUP, CIVILIAN_GROUP))
         //Custom Functionality
-        .insert(AnimationTimer(Timer::from_seconds(0.1, true)))
+        .insert(Animator::new(sheet))
         .insert(LivingBeing)
-        .insert(CivilianAnimationInfo {
-            state: CivilianState::Idle,
-            is_flip: false,
-        })
         .insert(Civilian);
 }
 
 fn civilian_state_system(
     mut cmds: Commands,
-    mut civilian_query: Query<(Entity, &Velocity, &mut CivilianAnimationInfo), With<Civilian>>,
+    mut civilian_query: Query<(Entity, &Velocity, &mut Animator), With<Civilian>>,
 ) {
-    for (mut civilian_entity, mut velocity, mut civilian_state) in civilian_query.get_single_mut() {
+    for (mut civilian_entity, mut velocity, mut animator) in civilian_query.get_single_mut() {
         if (velocity.linvel.x < 0.0) {
-            civilian_state.is_flip = true;
+            animator.facing_left = true;
         } else if (velocity.linvel.x > 0.0) {
-            civilian_state.is_flip = false;
+            animator.facing_left = false;
         }
 
         if (velocity.linvel == Vec2 { x: 0.0, y: 0.0 }) {
-            civilian_state.state = CivilianState::Idle;
+            animator.play("idle");
         } else {
-            civilian_state.state = CivilianState::Run;
+            animator.play("run");
         }
     }
 }
@@ -85,17 +94,11 @@ fn civilian_state_system(
 fn spawn_civilian_system(
     mut cmds: Commands,
     win_size: Res<WindowSize>,
-    asset_server: Res<AssetServer>,
-    mut texture_atlases: ResMut<Assets<TextureAtlas>>,
+    sprite_sheets: Res<SpriteSheets>,
+    mut rng: ResMut<GameRng>,
 ) {
-    let mut rng = rand::thread_rng();
     let mut num_civilians = 5;
 
-    let texture_handle =
-        asset_server.load("darians-assets/TeamGunner/CHARACTER_SPRITES/Green/Green_Soldier.png");
-    let texture_atlas = TextureAtlas::from_grid(texture_handle, Vec2::new(50.0, 50.0), 8, 5);
-    let texture_atlas_handle = texture_atlases.add(texture_atlas);
-
     for a in 0..num_civilians {
         let x = rng.gen_range(-win_size.w / 2.0..win_size.w / 2.0);
         let y = rng.gen_range(-win_size.h / 2.0..win_size.h / 2.0);
@@ -106,7 +109,7 @@ fn spawn_civilian_system(
                 rng.gen_range(-win_size.w / 2.0..win_size.w / 2.0),
                 rng.gen_range(-win_size.h / 2.0..win_size.h / 2.0),
             ),
-            &texture_atlas_handle,
+            sprite_sheets.get("civilian"),
         );
     }
 }
@@ -130,24 +133,31 @@ fn civilian_ai_system(
 fn civilian_despawn_system(
     mut cmds: Commands,
     mut send_civilian_hit: EventWriter<LivingBeingHitEvent>,
-    civilian_query: Query<Entity, With<Civilian>>,
-    player_query: Query<Entity, With<Player>>,
-    mut score: ResMut<PlayerScore>,
+    mut send_civilian_rescued: EventWriter<CivilianRescuedEvent>,
+    civilian_query: Query<(Entity, &Transform), With<Civilian>>,
+    player_query: Query<(Entity, &Lives, Option<&Dead>), With<Player>>,
     mut contact_events: EventReader<CollisionEvent>,
 ) {
-    let player = player_query.get_single().unwrap();
+    let (player, lives, dead) = player_query.get_single().unwrap();
+    //The civilians still out there were already lost when the last life ran out
+    if dead.is_some() && lives.lives_num == 0 {
+        contact_events.clear();
+        return;
+    }
     for event in contact_events.iter() {
         match event {
             CollisionEvent::Started(first, second, flags) => {
                 let first = *first;
                 let second = *second;
                 if flags == &CollisionEventFlags::empty() {
-                    for (civilian) in civilian_query.iter() {
+                    for (civilian, civilian_tf) in civilian_query.iter() {
                         //I think this will work because there is only 1 player, I guess this would work with more than 1 player
                         if ((first == player) ^ (second == player)) {
                             if ((first == civilian) ^ (second == civilian)) {
                                 cmds.entity(civilian).despawn_recursive();
-                                score.0 += 100;
+                                send_civilian_rescued.send(CivilianRescuedEvent {
+                                    position: civilian_tf.translation.truncate(),
+                                });
                             }
                         }
                     }
@@ -157,3 +167,21 @@ fn civilian_despawn_system(
         }
     }
 }
+
+//Nobody is coming for the civilians still out there once the player is out of lives
+fn civilian_lost_system(
+    mut life_lost_events: EventReader<LifeLostEvent>,
+    civilian_query: Query<&Transform, With<Civilian>>,
+    mut send_civilian_lost: EventWriter<CivilianLostEvent>,
+) {
+    if life_lost_events
+        .iter()
+        .any(|event| event.lives_remaining == 0)
+    {
+        for civilian_tf in civilian_query.iter() {
+            send_civilian_lost.send(CivilianLostEvent {
+                position: civilian_tf.translation.truncate(),
+            });
+        }
+    }
+}
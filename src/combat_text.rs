@@ -0,0 +1,206 @@
+use bevy::prelude::*;
+
+use crate::blaster::OverheatEvent;
+use crate::components::{Background, FloatingText, Player};
+use crate::constants::{FLOATING_TEXT_RISE_SPEED, FLOATING_TEXT_SECONDS, HIT_MARKER_SECONDS};
+use crate::projectile_collision::LivingBeingHitEvent;
+use crate::resources::GameFont;
+use crate::scoring::ScoreEvent;
+use crate::states::GameState;
+
+//World space feedback: damage numbers, score gains, overheat warnings and hit markers. Everything goes
+//through FloatingTextEvent so one system owns spawning, rising, fading and recycling the text entities
+pub struct CombatTextPlugin;
+
+impl Plugin for CombatTextPlugin {
+    fn build(&self, app: &mut App) {
+        app.add_event::<FloatingTextEvent>()
+            .insert_resource(FloatingTextPool(Vec::new()))
+            .add_system_set(
+                SystemSet::on_update(GameState::MainGame)
+                    .with_system(damage_text_system)
+                    .with_system(score_text_system)
+                    .with_system(overheat_text_system)
+                    .with_system(floating_text_system),
+            );
+    }
+}
+
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum FloatingTextKind {
+    Damage,
+    PowerShotDamage,
+    PlayerDamage,
+    Score,
+    Warning,
+    HitMarker,
+}
+
+impl FloatingTextKind {
+    fn color(&self) -> Color {
+        match self {
+            FloatingTextKind::Damage => Color::rgb(0.9, 0.9, 0.9),
+            FloatingTextKind::PowerShotDamage => Color::rgb(0.0, 1.0, 1.0),
+            FloatingTextKind::PlayerDamage => Color::rgb(1.0, 0.2, 0.2),
+            FloatingTextKind::Score => Color::rgb(1.0, 0.8, 0.0),
+            FloatingTextKind::Warning => Color::rgb(1.0, 0.1, 0.0),
+            FloatingTextKind::HitMarker => Color::rgb(1.0, 1.0, 1.0),
+        }
+    }
+
+    fn font_size(&self) -> f32 {
+        match self {
+            FloatingTextKind::PowerShotDamage | FloatingTextKind::Warning => 26.0,
+            FloatingTextKind::HitMarker => 18.0,
+            _ => 20.0,
+        }
+    }
+
+    fn seconds(&self) -> f32 {
+        match self {
+            FloatingTextKind::HitMarker => HIT_MARKER_SECONDS,
+            _ => FLOATING_TEXT_SECONDS,
+        }
+    }
+
+    //Hit markers stay where the shot landed, everything else drifts up
+    fn rise_speed(&self) -> f32 {
+        match self {
+            FloatingTextKind::HitMarker => 0.0,
+            _ => FLOATING_TEXT_RISE_SPEED,
+        }
+    }
+}
+
+pub struct FloatingTextEvent {
+    pub text: String,
+    pub position: Vec2,
+    pub kind: FloatingTextKind,
+}
+
+//Finished text entities waiting to be reused, there can be a lot of these during a big fight
+struct FloatingTextPool(Vec<Entity>);
+
+fn damage_text_system(
+    mut hit_events: EventReader<LivingBeingHitEvent>,
+    player_query: Query<(), With<Player>>,
+    mut send_text: EventWriter<FloatingTextEvent>,
+) {
+    for event in hit_events.iter() {
+        let kind = if player_query.contains(event.entity) {
+            FloatingTextKind::PlayerDamage
+        } else if event.power_shot {
+            FloatingTextKind::PowerShotDamage
+        } else {
+            FloatingTextKind::Damage
+        };
+        send_text.send(FloatingTextEvent {
+            text: "x".to_string(),
+            position: event.position,
+            kind: FloatingTextKind::HitMarker,
+        });
+        send_text.send(FloatingTextEvent {
+            text: event.damage.to_string(),
+            position: event.position + Vec2::new(0.0, 12.0),
+            kind,
+        });
+    }
+}
+
+fn score_text_system(
+    mut score_events: EventReader<ScoreEvent>,
+    mut send_text: EventWriter<FloatingTextEvent>,
+) {
+    for event in score_events.iter() {
+        send_text.send(FloatingTextEvent {
+            text: format!("+{}", event.points),
+            position: event.position,
+            kind: FloatingTextKind::Score,
+        });
+    }
+}
+
+fn overheat_text_system(
+    mut overheat_events: EventReader<OverheatEvent>,
+    mut send_text: EventWriter<FloatingTextEvent>,
+) {
+    for event in overheat_events.iter() {
+        send_text.send(FloatingTextEvent {
+            text: "OVERHEAT!".to_string(),
+            position: event.position + Vec2::new(0.0, 30.0),
+            kind: FloatingTextKind::Warning,
+        });
+    }
+}
+
+fn text_for(event: &FloatingTextEvent, font: &GameFont) -> Text {
+    Text::from_section(
+        event.text.clone(),
+        TextStyle {
+            font: font.0.clone(),
+            font_size: event.kind.font_size(),
+            color: event.kind.color(),
+        },
+    )
+    .with_alignment(TextAlignment::CENTER)
+}
+
+fn floating_text_system(
+    mut cmds: Commands,
+    time: Res<Time>,
+    font: Res<GameFont>,
+    mut pool: ResMut<FloatingTextPool>,
+    mut text_events: EventReader<FloatingTextEvent>,
+    mut query: Query<(
+        Entity,
+        &mut FloatingText,
+        &mut Transform,
+        &mut Text,
+        &mut Visibility,
+    )>,
+) {
+    for (entity, mut floating, mut transform, mut text, mut visibility) in query.iter_mut() {
+        if !visibility.is_visible {
+            continue;
+        }
+        floating.timer.tick(time.delta());
+        if floating.timer.finished() {
+            visibility.is_visible = false;
+            pool.0.push(entity);
+            continue;
+        }
+        transform.translation.y += floating.rise_speed * time.delta_seconds();
+        let alpha = 1.0 - floating.timer.percent();
+        for section in text.sections.iter_mut() {
+            section.style.color.set_a(alpha);
+        }
+    }
+
+    for event in text_events.iter() {
+        let floating = FloatingText {
+            timer: Timer::from_seconds(event.kind.seconds(), false),
+            rise_speed: event.kind.rise_speed(),
+        };
+        let translation = event.position.extend(10.0);
+
+        //Pooled entities go away with the rest of the session, so skip any that no longer exist
+        let pooled = std::iter::from_fn(|| pool.0.pop()).find(|entity| query.contains(*entity));
+        if let Some(entity) = pooled {
+            let (_, mut old, mut transform, mut text, mut visibility) =
+                query.get_mut(entity).unwrap();
+            *old = floating;
+            transform.translation = translation;
+            *text = text_for(event, &font);
+            visibility.is_visible = true;
+            continue;
+        }
+
+        cmds.spawn_bundle(Text2dBundle {
+            text: text_for(event, &font),
+            transform: Transform::from_translation(translation),
+            ..default()
+        })
+        .insert(floating)
+        .insert(Background);
+    }
+}
@@ -1,30 +1,40 @@
-use crate::{states::SpriteLocation, utils::CooldownTimer};
+use crate::{resources::SpawnType, utils::CooldownTimer};
 use bevy::prelude::*;
 use num_traits::ToPrimitive;
 use std::marker::{Send, Sync};
 
-#[derive(Component, Deref, DerefMut)]
-pub struct AnimationTimer(pub Timer);
-
 #[derive(Component)]
 pub struct AreaOfEffect(pub bool);
 
+#[derive(Component, Clone, Copy)]
+pub struct Archetype(pub SpawnType);
+
+//Entities in the arena that belong to a run but aren't alive, like shots and floating text.
+//Together with GameUi and LivingBeing this is everything a session has to clean up
+#[derive(Component)]
+pub struct Background;
+
 #[derive(Component)]
 pub struct Blaster {
     pub damage: u32,
+    pub power_shot: bool,
 }
 
 #[derive(Component)]
 pub struct Civilian;
 
+//Targets the player really shouldn't lose track of, like the last enemy holding up the next round
+#[derive(Component)]
+pub struct Critical;
+
 #[derive(Component)]
 pub struct Direction {
     pub is_right: bool,
 }
 
 #[derive(Component)]
+//The death animation sending "dispose" is what finally clears a dead being away
 pub struct Dead {
-    pub time_till_dispose: CooldownTimer,
     pub dying: bool,
 }
 
@@ -40,11 +50,39 @@ pub struct FromPlayer;
 #[derive(Component)]
 pub struct FromEnemy;
 
+//HUD elements that only exist while a run is going
+#[derive(Component)]
+pub struct GameUi;
+
+#[derive(Component)]
+pub struct FloatingText {
+    pub timer: Timer,
+    pub rise_speed: f32,
+}
+
+#[derive(Component)]
+pub struct HealthPip(pub u32);
+
+#[derive(Component)]
+pub struct HeatBar;
+
+#[derive(Component)]
+pub struct HeatWarningText;
+
+//HUD text that's scaled with the window, base_size is the size at the default 768 high window
+#[derive(Component)]
+pub struct HudText {
+    pub base_size: f32,
+}
+
 #[derive(Component)]
 pub struct Health {
     pub health: u32,
 }
 
+#[derive(Component)]
+pub struct LifeIcon(pub u32);
+
 #[derive(Component, Deref, DerefMut)]
 pub struct Lives {
     pub lives_num: u32,
@@ -61,6 +99,9 @@ pub struct PlayerLivesUI;
 #[derive(Component)]
 pub struct RoundUI;
 
+#[derive(Component)]
+pub struct ComboUi;
+
 #[derive(Component)]
 pub struct ScoreUi;
 
@@ -75,6 +116,8 @@ pub struct WeaponData {
     pub firing: bool,
     pub fire_rate_timer: CooldownTimer,
     pub damage: u32,
+    //Which look from assets/projectiles.ron its shots get
+    pub projectile: &'static str,
 }
 
 //Default is qwark's ranged weapon
@@ -85,6 +128,7 @@ impl Default for WeaponData {
             firing: false,
             fire_rate_timer: CooldownTimer::from_seconds(1.0),
             damage: 1,
+            projectile: "blaster",
         }
     }
 }
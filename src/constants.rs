@@ -11,6 +11,10 @@ pub const PLAYER_ATTRACTION_FORCE: f32 = 3.0;
 pub const PLAYER_HEALTH: u32 = 3;
 pub const PLAYER_LIVES: u32 = 3;
 pub const ENEMY_SPEED: f32 = 100.0;
+pub const CRAB_HEALTH: u32 = 1;
+pub const BOT_HEALTH: u32 = 2;
+pub const EXPLODER_HEALTH: u32 = 1;
+pub const TANK_HEALTH: u32 = 5;
 
 //Blaster Constants
 pub const BLASTER_SHOT_HEAT_ADDITION: f32 = 5.;
@@ -19,9 +23,107 @@ pub const MAX_BLASTER_HEAT: f32 = 100.;
 pub const BLASTER_COOLOFF_MULTIPLIER: f32 = 3.5;
 pub const COOLDOWN_TIME_SECONDS: f32 = 5.;
 pub const BLASTER_SPEED: f32 = 200.0;
+pub const BLASTER_DAMAGE: u32 = 1;
+pub const POWER_SHOT_DAMAGE: u32 = 3;
+
+//Scoring Constants
+pub const CRAB_POINTS: usize = 50;
+pub const BOT_POINTS: usize = 100;
+pub const EXPLODER_POINTS: usize = 150;
+pub const TANK_POINTS: usize = 250;
+pub const POWER_SHOT_KILL_BONUS: usize = 25;
+pub const CIVILIAN_RESCUE_POINTS: usize = 100;
+pub const COMBO_WINDOW_SECONDS: f32 = 2.0;
+pub const COMBO_KILLS_PER_STEP: u32 = 3;
+pub const MAX_COMBO_MULTIPLIER: u32 = 5;
+
+//Endless Constants
+pub const ENDLESS_STARTING_BUDGET: u32 = 2;
+pub const ENDLESS_BUDGET_GROWTH: u32 = 2;
+pub const ENDLESS_STARTING_MAX_SPAWNS: u32 = 2;
+pub const ENDLESS_MAX_SPAWNS_CAP: u32 = 12;
+pub const CRAB_COST: u32 = 1;
+pub const BOT_COST: u32 = 2;
+pub const EXPLODER_COST: u32 = 3;
+pub const TANK_COST: u32 = 5;
+
+//Round Constants
+pub const INTERMISSION_SECONDS: f32 = 5.;
+
+//High Score Constants
+pub const MAX_HIGH_SCORES: usize = 10;
+pub const MAX_NAME_LENGTH: usize = 10;
+
+//Settings Constants
+pub const RESOLUTIONS: [(f32, f32); 5] = [
+    (1024., 768.),
+    (1280., 720.),
+    (1280., 960.),
+    (1600., 900.),
+    (1920., 1080.),
+];
+pub const VOLUME_STEP: f32 = 0.1;
+pub const MIN_STICK_DEADZONE: f32 = 0.05;
+pub const MAX_STICK_DEADZONE: f32 = 0.5;
+pub const STICK_DEADZONE_STEP: f32 = 0.05;
+pub const DEFAULT_STICK_DEADZONE: f32 = 0.2;
+
+//Combat Text Constants
+pub const FLOATING_TEXT_SECONDS: f32 = 0.8;
+pub const FLOATING_TEXT_RISE_SPEED: f32 = 40.0;
+pub const HIT_MARKER_SECONDS: f32 = 0.15;
+
+//HUD Constants
+pub const HUD_REFERENCE_HEIGHT: f32 = 768.;
+
+//Indicator Constants
+pub const INDICATOR_SIZE: f32 = 12.0;
+pub const INDICATOR_EDGE_MARGIN: f32 = 20.0;
+pub const CRITICAL_INDICATOR_SCALE: f32 = 1.8;
+
+//Banner Constants
+pub const BANNER_SLIDE_SECONDS: f32 = 0.35;
+pub const ROUND_BANNER_HOLD_SECONDS: f32 = 1.8;
+pub const WARNING_BANNER_HOLD_SECONDS: f32 = 1.0;
+pub const CIVILIAN_ALERT_COOLDOWN_SECONDS: f32 = 6.0;
+
+//Depth Constants
+pub const Y_SORT_MIN_Z: f32 = 1.0;
+pub const Y_SORT_MAX_Z: f32 = 4.0;
+pub const Y_SORT_SCALE: f32 = 0.0005;
+pub const SHADOW_SIZE: (f32, f32) = (20.0, 8.0);
+pub const SHADOW_OFFSET: (f32, f32) = (0.0, -14.0);
+pub const SHADOW_ALPHA: f32 = 0.5;
+
+//Tint Constants
+pub const HIT_FLASH_SECONDS: f32 = 0.12;
+pub const HIT_FLASH_BRIGHTNESS: f32 = 4.0;
+pub const LOW_HEALTH_FRACTION: f32 = 0.34;
+
+//Music Constants
+pub const MUSIC_CROSSFADE_SECONDS: f32 = 1.5;
+pub const MUSIC_PAUSED_VOLUME: f32 = 0.4;
+pub const MUSIC_ENEMY_CAP: f32 = 8.0;
+pub const MUSIC_DAMAGE_CAP: f32 = 3.0;
+pub const MUSIC_DAMAGE_DECAY: f32 = 0.3;
+pub const MUSIC_ENEMY_WEIGHT: f32 = 0.5;
+pub const MUSIC_DAMAGE_WEIGHT: f32 = 0.35;
+pub const MUSIC_HEAT_WEIGHT: f32 = 0.3;
+
+//Announcer Constants
+pub const QUIP_GAP_SECONDS: f32 = 4.0;
+pub const QUIP_BASE_SECONDS: f32 = 1.5;
+pub const QUIP_SECONDS_PER_CHARACTER: f32 = 0.05;
+pub const QUIP_FADE_SECONDS: f32 = 0.3;
+pub const MULTIKILL_KILLS: usize = 3;
+pub const MULTIKILL_WINDOW_SECONDS: f32 = 1.5;
+
+//Replay Constants
+pub const REPLAY_SPEEDS: [f32; 4] = [1.0, 2.0, 4.0, 8.0];
 
 //Other Constants
 pub const TIME_STEP: f32 = 1. / 60.;
+pub const MAX_STEPS_PER_FRAME: u32 = 4;
 pub const KNOCKBACK_POWER: f32 = 500.0;
 
 //Collision Group Flags
@@ -0,0 +1,117 @@
+use bevy::ecs::system::SystemParam;
+use bevy::prelude::*;
+use serde::{Deserialize, Serialize};
+
+use crate::components::Player;
+use crate::fixed_step::{FixedStepApp, FixedSystems};
+use crate::resources::{Controller, WindowSize};
+use crate::settings::Settings;
+use crate::states::GameState;
+
+//Turns the keyboard, mouse and gamepad into a PlayerInput each frame. Gameplay only ever reads
+//PlayerInput, so anything else that fills it in (like a headless script) can stand in for a person
+pub struct ControlsPlugin;
+
+impl Plugin for ControlsPlugin {
+    fn build(&self, app: &mut App) {
+        app.add_fixed_systems(
+            FixedSystems::on_update(GameState::MainGame)
+                .with_system(read_player_input_system.label(ReadInputLabel)),
+        );
+    }
+}
+
+//Whatever fills in PlayerInput runs with this label, the player's systems run after it
+#[derive(SystemLabel, Debug, Clone, PartialEq, Eq, Hash)]
+pub struct ReadInputLabel;
+
+#[derive(Clone, Copy, Debug, Default, PartialEq, Serialize, Deserialize)]
+pub struct PlayerInput {
+    //Each axis from -1 to 1
+    pub movement: Vec2,
+    //Where to shoot relative to the player, doesn't need to be normalised. Zero when there's nothing to aim with
+    pub aim: Vec2,
+    pub firing: bool,
+    pub jump: bool,
+    pub crouch: bool,
+}
+
+#[derive(SystemParam)]
+struct GamepadInput<'w, 's> {
+    controller: Option<Res<'w, Controller>>,
+    axes: Res<'w, Axis<GamepadAxis>>,
+    buttons: Res<'w, Input<GamepadButton>>,
+    #[system_param(ignore)]
+    _marker: std::marker::PhantomData<&'s ()>,
+}
+
+#[derive(SystemParam)]
+struct MouseInput<'w, 's> {
+    buttons: Res<'w, Input<MouseButton>>,
+    windows: Res<'w, Windows>,
+    win_size: Res<'w, WindowSize>,
+    #[system_param(ignore)]
+    _marker: std::marker::PhantomData<&'s ()>,
+}
+
+fn read_player_input_system(
+    mut input: ResMut<PlayerInput>,
+    player_query: Query<&Transform, With<Player>>,
+    gamepad: GamepadInput,
+    mouse: MouseInput,
+    keys: Res<Input<KeyCode>>,
+    settings: Res<Settings>,
+) {
+    let bindings = &settings.bindings;
+    let (controller, axes, buttons) = (&gamepad.controller, &gamepad.axes, &gamepad.buttons);
+    let mut new_input = PlayerInput::default();
+
+    if let Some(controller) = controller.as_ref() {
+        let axis_lx = GamepadAxis::new(controller.0, GamepadAxisType::LeftStickX);
+        let axis_ly = GamepadAxis::new(controller.0, GamepadAxisType::LeftStickY);
+        if let (Some(x), Some(y)) = (axes.get(axis_lx), axes.get(axis_ly)) {
+            new_input.movement = Vec2::new(x, y);
+        }
+    } else {
+        if keys.pressed(bindings.up) {
+            new_input.movement.y = 1.;
+        } else if keys.pressed(bindings.down) {
+            new_input.movement.y = -1.;
+        }
+        if keys.pressed(bindings.right) {
+            new_input.movement.x = 1.;
+        } else if keys.pressed(bindings.left) {
+            new_input.movement.x = -1.;
+        }
+        new_input.jump = keys.pressed(bindings.jump);
+        new_input.crouch = keys.pressed(bindings.crouch);
+    }
+
+    //The cursor is in window coordinates, the player in world coordinates centred on the window
+    let (win_size, windows) = (&mouse.win_size, &mouse.windows);
+    let cursor = windows
+        .get_primary()
+        .and_then(|window| window.cursor_position());
+    if let (Some(cursor), Ok(player_tf)) = (cursor, player_query.get_single()) {
+        new_input.aim = Vec2::new(
+            cursor.x - win_size.w / 2.0 - player_tf.translation.x,
+            cursor.y - win_size.h / 2.0 - player_tf.translation.y,
+        );
+    }
+    new_input.firing = mouse.buttons.pressed(MouseButton::Left);
+
+    if let Some(controller) = controller.as_ref() {
+        let normal_fire_button = GamepadButton::new(controller.0, GamepadButtonType::LeftTrigger);
+        new_input.firing = buttons.pressed(normal_fire_button);
+
+        let axis_rx = GamepadAxis::new(controller.0, GamepadAxisType::RightStickX);
+        let axis_ry = GamepadAxis::new(controller.0, GamepadAxisType::RightStickY);
+        if let (Some(x), Some(y)) = (axes.get(axis_rx), axes.get(axis_ry)) {
+            if x.abs() > settings.stick_deadzone || y.abs() > settings.stick_deadzone {
+                new_input.aim = Vec2::new(x, y);
+            }
+        }
+    }
+
+    *input = new_input;
+}
@@ -1,3 +1,17 @@
+//Lets the blaster fire without ever overheating
 pub fn is_overheat_disabled() -> bool {
-    std::option_env!("MBRH_DISABLE_OVERHEAT").is_some()
+    std::env::var_os("MBRH_DISABLE_OVERHEAT").is_some()
+}
+
+//Writes the stats of every run to this file when the game ends, a .csv gets one row per round and
+//anything else gets the whole run as json
+pub fn stats_export_path() -> Option<std::path::PathBuf> {
+    std::env::var_os("MBRH_STATS_EXPORT").map(std::path::PathBuf::from)
+}
+
+//Lets a run be played again, the seed of every run is logged when it starts
+pub fn seed_override() -> Option<u64> {
+    std::env::var("MBRH_SEED")
+        .ok()
+        .and_then(|seed| seed.parse().ok())
 }
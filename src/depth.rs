@@ -0,0 +1,59 @@
+use bevy::prelude::*;
+
+use crate::components::{Blaster, LivingBeing};
+use crate::constants::{
+    SHADOW_ALPHA, SHADOW_OFFSET, SHADOW_SIZE, Y_SORT_MAX_Z, Y_SORT_MIN_Z, Y_SORT_SCALE,
+};
+use crate::resources::GameTextures;
+
+//Fakes depth in the top down arena: anything lower on screen is drawn in front, and every living being
+//gets a shadow at its feet. Sorted z stays below the particles, floating text and indicators
+pub struct DepthPlugin;
+
+impl Plugin for DepthPlugin {
+    fn build(&self, app: &mut App) {
+        app.add_system(attach_shadows_system)
+            .add_system(y_sort_system);
+    }
+}
+
+#[derive(Component)]
+pub struct Shadow;
+
+type SortedEntities = Or<(With<LivingBeing>, With<Blaster>)>;
+
+fn attach_shadows_system(
+    mut cmds: Commands,
+    game_textures: Res<GameTextures>,
+    query: Query<Entity, Added<LivingBeing>>,
+) {
+    for entity in query.iter() {
+        //A child so it follows along and goes when its owner is despawned. The offset is in the owner's
+        //sprite space and the tiny negative z keeps it just underneath
+        let shadow = cmds
+            .spawn_bundle(SpriteBundle {
+                sprite: Sprite {
+                    color: Color::rgba(1.0, 1.0, 1.0, SHADOW_ALPHA),
+                    custom_size: Some(Vec2::new(SHADOW_SIZE.0, SHADOW_SIZE.1)),
+                    ..default()
+                },
+                texture: game_textures.shadow.clone(),
+                transform: Transform::from_xyz(SHADOW_OFFSET.0, SHADOW_OFFSET.1, -0.01),
+                ..default()
+            })
+            .insert(Shadow)
+            .id();
+        cmds.entity(entity).add_child(shadow);
+    }
+}
+
+fn y_sort_system(mut query: Query<&mut Transform, SortedEntities>) {
+    for mut transform in query.iter_mut() {
+        let middle = (Y_SORT_MIN_Z + Y_SORT_MAX_Z) / 2.0;
+        let z = (middle - transform.translation.y * Y_SORT_SCALE).clamp(Y_SORT_MIN_Z, Y_SORT_MAX_Z);
+        //Only touch the transform when it actually moves so resting bodies aren't marked as changed
+        if (transform.translation.z - z).abs() > f32::EPSILON {
+            transform.translation.z = z;
+        }
+    }
+}
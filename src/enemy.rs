@@ -1,3 +1,5 @@
+use std::sync::Arc;
+
 use bevy::prelude::*;
 use bevy::utils::HashMap;
 use bevy_rapier2d::parry::either::Either::Right;
@@ -6,17 +8,21 @@ use rand::Rng;
 
 use crate::blaster::BlasterFiredEvent;
 use crate::components::{
-    AnimationTimer, AreaOfEffect, Dead, Dispose, Enemy, FromPlayer, Health, Lives, LivingBeing,
-    Player, WeaponData,
+    Archetype, AreaOfEffect, Dead, Dispose, Enemy, FromPlayer, Health, Lives, LivingBeing, Player,
+    WeaponData,
 };
 use crate::constants::{
-    ENEMY_GROUP, ENEMY_REPULSION_FORCE, ENEMY_REPULSION_RADIUS, ENEMY_SPEED, ENEMY_SPRITE_SCALE,
-    PHYSICAL_GROUP, PLAYER_ATTRACTION_FORCE, PLAYER_GROUP, PLAYER_HEIGHT, PLAYER_SPEED,
-    PLAYER_SPRITE_SCALE, PLAYER_WIDTH, TIME_STEP,
+    BOT_HEALTH, CRAB_HEALTH, ENEMY_GROUP, ENEMY_REPULSION_FORCE, ENEMY_REPULSION_RADIUS,
+    ENEMY_SPEED, ENEMY_SPRITE_SCALE, EXPLODER_HEALTH, PHYSICAL_GROUP, PLAYER_ATTRACTION_FORCE,
+    PLAYER_GROUP, PLAYER_HEIGHT, PLAYER_SPEED, PLAYER_SPRITE_SCALE, PLAYER_WIDTH, TANK_HEALTH,
+    TIME_STEP,
 };
+use crate::fixed_step::{FixedStepApp, FixedSystems, FixedTime};
+use crate::graphics::{AnimationEvent, Animator, SpriteSheet, SpriteSheets};
 use crate::projectile_collision::{KnockBackEvent, LivingBeingDeathEvent, LivingBeingHitEvent};
-use crate::resources::{GameTextures, WindowSize};
-use crate::states::{EnemyAnimationInfo, EnemyState, GameState};
+use crate::resources::{GameRng, GameTextures, SpawnType, WindowSize};
+use crate::settings::Settings;
+use crate::states::GameState;
 use crate::utils::{normalize_vec2, CooldownTimer};
 use crate::{blaster, PlayerScore};
 
@@ -24,12 +30,12 @@ pub struct EnemyPlugin;
 
 impl Plugin for EnemyPlugin {
     fn build(&self, app: &mut App) {
-        app.add_event::<LivingBeingHitEvent>()
-            .add_event::<LivingBeingDeathEvent>()
-            .add_event::<BlasterFiredEvent>()
-            .add_event::<KnockBackEvent>()
-            .add_system_set(
-                SystemSet::on_update(GameState::MainGame)
+        app.add_fixed_event::<LivingBeingHitEvent>()
+            .add_fixed_event::<LivingBeingDeathEvent>()
+            .add_fixed_event::<BlasterFiredEvent>()
+            .add_fixed_event::<KnockBackEvent>()
+            .add_fixed_systems(
+                FixedSystems::on_update(GameState::MainGame)
                     .with_system(enemy_ai_system)
                     .with_system(enemy_blaster_system)
                     .with_system(enemy_state_system)
@@ -38,10 +44,22 @@ impl Plugin for EnemyPlugin {
     }
 }
 
-pub fn spawn_crab(
+//Only crabs have their own sprites and AI so far, the other archetypes are crabs that take more shots
+fn enemy_health(archetype: SpawnType) -> u32 {
+    match archetype {
+        SpawnType::Crab => CRAB_HEALTH,
+        SpawnType::Bot => BOT_HEALTH,
+        SpawnType::Tank => TANK_HEALTH,
+        SpawnType::Exploder => EXPLODER_HEALTH,
+        SpawnType::Civilian => 0,
+    }
+}
+
+pub fn spawn_enemy(
     cmds: &mut Commands,
+    archetype: SpawnType,
     position: Vec2,
-    texture_atlas_handle: &Handle<TextureAtlas>,
+    sheet: &Arc<SpriteSheet>,
 ) {
     //Ripped my own code from the animation branch
     // Add the enemy sprites I think I want to break this out into a component? With a bunch of parts that we can call in different systems even at startup
@@ -53,7 +71,7 @@ pub fn spawn_crab(
     };
 
     let sprite = SpriteSheetBundle {
-        texture_atlas: texture_atlas_handle.clone(),
+        texture_atlas: sheet.atlas.clone(),
         transform: transform,
         ..default()
     };
@@ -72,45 +90,39 @@ pub fn spawn_crab(
             ENEMY_GROUP | PHYSICAL_GROUP,
         ))
         //Custom functionality
-        .insert(AnimationTimer(Timer::from_seconds(0.1, true)))
+        .insert(Animator::new(sheet))
         .insert(LivingBeing)
-        .insert(Health { health: 1 })
+        .insert(Health {
+            health: enemy_health(archetype),
+        })
         .insert(Lives { lives_num: 1 })
         .insert(Enemy)
-        .insert(EnemyAnimationInfo {
-            state: EnemyState::Idle,
-            is_flip: false,
-        })
+        .insert(Archetype(archetype))
         // .insert(Direction { is_right: true }); - This was supposed to be a concept for moving up down left and right each direction having different
         // sprites. Just gna make it a jira issue for now
         .insert(WeaponData {
             firing: true,
+            projectile: "enemy_blaster",
             ..Default::default()
         });
 }
 
 fn enemy_spawn_system(
     mut cmds: Commands,
-    asset_server: Res<AssetServer>,
-    mut texture_atlases: ResMut<Assets<TextureAtlas>>,
+    sprite_sheets: Res<SpriteSheets>,
     win_size: Res<WindowSize>,
+    mut rng: ResMut<GameRng>,
 ) {
-    let mut rng = rand::thread_rng();
-
-    let texture_handle =
-        asset_server.load("darians-assets/TeamGunner/CHARACTER_SPRITES/Red/Red_Soldier.png");
-    let texture_atlas = TextureAtlas::from_grid(texture_handle, Vec2::new(50.0, 50.0), 8, 5);
-    let texture_atlas_handle = texture_atlases.add(texture_atlas);
-
     // Add the enemy
     for i in 0..2 {
-        spawn_crab(
+        spawn_enemy(
             &mut cmds,
+            SpawnType::Crab,
             Vec2::new(
                 rng.gen_range(-win_size.w / 2.0..win_size.w / 2.0),
                 rng.gen_range(-win_size.h / 2.0..win_size.h / 2.0),
             ),
-            &texture_atlas_handle,
+            sprite_sheets.get("crab"),
         );
     }
 }
@@ -119,8 +131,10 @@ fn enemy_ai_system(
     mut cmds: Commands,
     mut enemy_query: Query<(Entity, &mut Velocity, &Transform), (With<Enemy>, Without<Dead>)>,
     player_query: Query<(&Transform), With<Player>>,
+    settings: Res<Settings>,
 ) {
     let player_tf = player_query.get_single().unwrap();
+    let enemy_speed = ENEMY_SPEED * settings.difficulty.enemy_speed_multiplier();
 
     for (enemy, mut enemy_velocity, enemy_tf) in enemy_query.iter_mut() {
         let position_diff = Vec2::new(
@@ -128,28 +142,25 @@ fn enemy_ai_system(
             player_tf.translation.y - enemy_tf.translation.y,
         );
 
-        enemy_velocity.linvel = position_diff.normalize() * ENEMY_SPEED;
+        enemy_velocity.linvel = position_diff.normalize() * enemy_speed;
     }
 }
 
 fn enemy_state_system(
     mut cmds: Commands,
-    mut enemy_query: Query<
-        (Entity, &Velocity, &mut EnemyAnimationInfo),
-        (With<Enemy>, Without<Dead>),
-    >,
+    mut enemy_query: Query<(Entity, &Velocity, &mut Animator), (With<Enemy>, Without<Dead>)>,
 ) {
-    for (mut enemy_entity, mut velocity, mut enemy_state) in enemy_query.get_single_mut() {
+    for (mut enemy_entity, mut velocity, mut animator) in enemy_query.get_single_mut() {
         if (velocity.linvel.x < 0.0) {
-            enemy_state.is_flip = true;
+            animator.facing_left = true;
         } else if (velocity.linvel.x > 0.0) {
-            enemy_state.is_flip = false;
+            animator.facing_left = false;
         }
 
         if (velocity.linvel == Vec2 { x: 0.0, y: 0.0 }) {
-            enemy_state.state = EnemyState::Idle;
+            animator.play("idle");
         } else {
-            enemy_state.state = EnemyState::Run;
+            animator.play("run");
         }
     }
 }
@@ -159,7 +170,7 @@ fn enemy_blaster_system(
     mut enemy_query: Query<(Entity, &Transform, &mut WeaponData), (With<Enemy>, Without<Dead>)>,
     mut send_fire_event: EventWriter<BlasterFiredEvent>,
     player_query: Query<(&Transform), With<Player>>,
-    time: Res<Time>,
+    time: Res<FixedTime>,
 ) {
     let player_tf = player_query.get_single().unwrap();
 
@@ -180,7 +191,8 @@ fn enemy_blaster_system(
                 from_player: false,
                 memberships: PLAYER_GROUP,
                 filter: PLAYER_GROUP,
-                color: Color::rgb(1.0, 0.0, 0.0),
+                projectile: enemy_weapon.projectile,
+                power_shot: false,
             };
             send_fire_event.send(event);
         }
@@ -190,23 +202,22 @@ fn enemy_blaster_system(
 //Fix the dead tag to be inside the with
 fn enemy_dying(
     mut enemy_query: Query<
-        (Entity, &mut EnemyAnimationInfo, &mut Velocity, &mut Dead),
+        (Entity, &mut Animator, &mut Velocity, &mut Dead),
         (With<(Enemy)>, Without<Dispose>),
     >,
     mut commands: Commands,
-    time: Res<Time>,
+    mut animation_events: EventReader<AnimationEvent>,
 ) {
-    for (enemy, mut enemy_state, mut velocity, mut dead) in enemy_query.iter_mut() {
-        enemy_state.state = EnemyState::Death;
+    for (enemy, mut animator, mut velocity, mut dead) in enemy_query.iter_mut() {
+        animator.play("death");
         velocity.linvel = Vec2::new(0.0, 0.0);
-        if (!dead.dying) {
-            dead.dying = true;
-            dead.time_till_dispose.trigger();
-        }
-        dead.time_till_dispose.tick(time.delta());
+        dead.dying = true;
+    }
 
-        if (dead.time_till_dispose.ready()) {
-            commands.entity(enemy).insert(Dispose);
+    //The corpse stays until its death animation has played out
+    for event in animation_events.iter() {
+        if event.name == "dispose" && enemy_query.contains(event.entity) {
+            commands.entity(event.entity).insert(Dispose);
         }
     }
 }
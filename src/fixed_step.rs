@@ -0,0 +1,308 @@
+use std::time::Duration;
+
+use bevy::ecs::event::{Event, Events};
+use bevy::ecs::schedule::{
+    IntoSystemDescriptor, ParallelSystemDescriptor, ShouldRun, SystemDescriptor,
+};
+use bevy::prelude::*;
+use bevy_rapier2d::prelude::*;
+
+use crate::constants::{MAX_STEPS_PER_FRAME, TIME_STEP};
+use crate::states::GameState;
+
+//Gameplay runs in ticks of exactly TIME_STEP, as many as the real time since the last frame covers, so
+//the same seed and inputs always play out the same way whatever the frame rate. Physics steps inside the
+//same tick. Gameplay events are cleared on the first tick of each frame that ticks, so they last until
+//both the ticks and the once a frame systems in Update (sounds, effects, the HUD) have seen them, however
+//many ticks a frame runs
+pub struct FixedStepPlugin;
+
+impl Plugin for FixedStepPlugin {
+    fn build(&self, app: &mut App) {
+        app.insert_resource(FixedTime::default())
+            .insert_resource(FixedSystemOrder::default())
+            .insert_resource(RapierConfiguration {
+                timestep_mode: TimestepMode::Fixed {
+                    dt: TIME_STEP,
+                    substeps: 1,
+                },
+                ..default()
+            })
+            .add_plugin(
+                RapierPhysicsPlugin::<NoUserData>::pixels_per_meter(100.0)
+                    .with_default_system_setup(false),
+            )
+            .add_stage_after(
+                CoreStage::Update,
+                FixedStepStage,
+                Schedule::default()
+                    .with_run_criteria(fixed_step_criteria)
+                    .with_stage(FixedStage::First, SystemStage::single_threaded())
+                    .with_stage(FixedStage::Update, SystemStage::single_threaded())
+                    .with_stage(
+                        PhysicsStages::SyncBackend,
+                        physics_stage(PhysicsStages::SyncBackend),
+                    )
+                    .with_stage(
+                        PhysicsStages::StepSimulation,
+                        physics_stage(PhysicsStages::StepSimulation),
+                    )
+                    .with_stage(
+                        PhysicsStages::Writeback,
+                        physics_stage(PhysicsStages::Writeback),
+                    ),
+            )
+            //Despawns still need catching at the end of a frame that didn't tick, same as rapier does it
+            .add_stage_before(
+                CoreStage::Last,
+                PhysicsStages::DetectDespawn,
+                physics_stage(PhysicsStages::DetectDespawn),
+            );
+    }
+}
+
+//The same stages rapier sets up when it's left to do it itself
+fn physics_stage(stage: PhysicsStages) -> SystemStage {
+    SystemStage::parallel().with_system_set(RapierPhysicsPlugin::<NoUserData>::get_systems(stage))
+}
+
+#[derive(Debug, Hash, PartialEq, Eq, Clone, StageLabel)]
+pub struct FixedStepStage;
+
+#[derive(Debug, Hash, PartialEq, Eq, Clone, StageLabel)]
+enum FixedStage {
+    First,
+    Update,
+}
+
+//The gameplay clock, use it in place of Time in anything that runs on the fixed step
+pub struct FixedTime {
+    elapsed: Duration,
+    //Real time not yet covered by a tick
+    accumulator: Duration,
+    ticks: u64,
+    //Set while ticking through a frame's worth of steps, so the frame's time is only added once
+    looping: bool,
+    //Ticks run so far this frame
+    frame_ticks: u32,
+    //How many seconds of play go by per real second, replays are watched sped up
+    speed: f32,
+    //Stopped outright rather than through the pause menu, with single ticks let through on request
+    paused: bool,
+    step_requested: bool,
+}
+
+impl Default for FixedTime {
+    fn default() -> Self {
+        Self {
+            elapsed: Duration::ZERO,
+            accumulator: Duration::ZERO,
+            ticks: 0,
+            looping: false,
+            frame_ticks: 0,
+            speed: 1.0,
+            paused: false,
+            step_requested: false,
+        }
+    }
+}
+
+impl FixedTime {
+    pub fn step() -> Duration {
+        Duration::from_secs_f32(TIME_STEP)
+    }
+
+    pub fn delta(&self) -> Duration {
+        Self::step()
+    }
+
+    pub fn delta_seconds(&self) -> f32 {
+        TIME_STEP
+    }
+
+    pub fn seconds_since_startup(&self) -> f64 {
+        self.elapsed.as_secs_f64()
+    }
+
+    pub fn ticks(&self) -> u64 {
+        self.ticks
+    }
+
+    pub fn speed(&self) -> f32 {
+        self.speed
+    }
+
+    pub fn set_speed(&mut self, speed: f32) {
+        self.speed = speed;
+    }
+
+    pub fn paused(&self) -> bool {
+        self.paused
+    }
+
+    //Whatever time was left over is dropped, so pausing mid frame stops after the tick that's running
+    pub fn set_paused(&mut self, paused: bool) {
+        self.paused = paused;
+        self.accumulator = Duration::ZERO;
+        self.step_requested = false;
+    }
+
+    //While paused, plays exactly one tick on the next frame
+    pub fn step_once(&mut self) {
+        self.step_requested = self.paused;
+    }
+}
+
+//After a long frame (loading, dragging the window) the game slows down rather than trying to catch up
+//on seconds of ticks at once. Sped up, the cap goes up with it
+//A tick that asks for a new state also ends the frame's ticks, the state only changes over in Update and
+//the ticks after it would otherwise carry on as if it hadn't been asked for
+fn fixed_step_criteria(
+    time: Res<Time>,
+    state: Res<State<GameState>>,
+    mut fixed_time: ResMut<FixedTime>,
+) -> ShouldRun {
+    let step = FixedTime::step();
+    if fixed_time.looping && state.is_changed() {
+        fixed_time.looping = false;
+        return ShouldRun::No;
+    }
+    if !fixed_time.looping {
+        fixed_time.frame_ticks = 0;
+        let frame_time = if !fixed_time.paused {
+            time.delta().mul_f32(fixed_time.speed)
+        } else if std::mem::take(&mut fixed_time.step_requested) {
+            step
+        } else {
+            Duration::ZERO
+        };
+        let max_frame_time = step.mul_f32(MAX_STEPS_PER_FRAME as f32 * fixed_time.speed.max(1.0));
+        fixed_time.accumulator = (fixed_time.accumulator + frame_time).min(max_frame_time);
+    }
+    if fixed_time.accumulator >= step {
+        fixed_time.accumulator -= step;
+        fixed_time.elapsed += step;
+        fixed_time.ticks += 1;
+        fixed_time.frame_ticks += 1;
+        fixed_time.looping = true;
+        ShouldRun::YesAndCheckAgain
+    } else {
+        fixed_time.looping = false;
+        ShouldRun::No
+    }
+}
+
+fn first_tick_of_frame(fixed_time: Res<FixedTime>) -> ShouldRun {
+    if fixed_time.frame_ticks == 1 {
+        ShouldRun::Yes
+    } else {
+        ShouldRun::No
+    }
+}
+
+//Systems for the fixed step. Like a SystemSet, except they run one after another in the order they're
+//added: bevy picks a new order for systems that aren't ordered against each other every launch, which is
+//enough for two runs with the same inputs to come apart
+#[derive(Default)]
+pub struct FixedSystems {
+    state: Option<GameState>,
+    systems: Vec<ParallelSystemDescriptor>,
+}
+
+impl FixedSystems {
+    //The fixed step's SystemSet::on_update. That one loops until the state driver says it's done, and the
+    //driver runs in Update, so here the state is just checked once a tick
+    pub fn on_update(state: GameState) -> Self {
+        Self {
+            state: Some(state),
+            systems: Vec::new(),
+        }
+    }
+
+    pub fn with_system<Params>(mut self, system: impl IntoSystemDescriptor<Params>) -> Self {
+        match system.into_descriptor() {
+            SystemDescriptor::Parallel(system) => self.systems.push(system),
+            SystemDescriptor::Exclusive(_) => {
+                panic!("Exclusive systems can't go on the fixed step")
+            }
+        }
+        self
+    }
+}
+
+fn in_state(state: GameState) -> impl FnMut(Res<State<GameState>>) -> ShouldRun {
+    move |current: Res<State<GameState>>| {
+        if *current.current() == state {
+            ShouldRun::Yes
+        } else {
+            ShouldRun::No
+        }
+    }
+}
+
+#[derive(Clone, Copy)]
+struct FixedSystemLabel(&'static str);
+
+impl SystemLabel for FixedSystemLabel {
+    fn as_str(&self) -> &'static str {
+        self.0
+    }
+}
+
+//Each system on the fixed step goes after the one added before it
+#[derive(Default)]
+struct FixedSystemOrder {
+    count: usize,
+    last: Option<FixedSystemLabel>,
+}
+
+pub trait FixedStepApp {
+    fn add_fixed_systems(&mut self, systems: FixedSystems) -> &mut Self;
+    fn add_fixed_system<Params>(&mut self, system: impl IntoSystemDescriptor<Params>) -> &mut Self;
+    //Like add_event, but the events are cleared on the fixed step, so one sent on a tick is still there for
+    //the rest of that frame's ticks and for Update on the next frame. For anything gameplay sends or reads
+    fn add_fixed_event<T: Event>(&mut self) -> &mut Self;
+}
+
+impl FixedStepApp for App {
+    fn add_fixed_systems(&mut self, systems: FixedSystems) -> &mut Self {
+        for mut system in systems.systems {
+            if let Some(state) = systems.state.clone() {
+                system = system.with_run_criteria(in_state(state));
+            }
+            let mut order = self.world.resource_mut::<FixedSystemOrder>();
+            order.count += 1;
+            //Labels are static strings, there's only ever a few dozen of these
+            let label = FixedSystemLabel(Box::leak(
+                format!("fixed step system {}", order.count).into_boxed_str(),
+            ));
+            system = system.label(label);
+            if let Some(last) = order.last.replace(label) {
+                system = system.after(last);
+            }
+            self.schedule
+                .stage(FixedStepStage, |schedule: &mut Schedule| {
+                    schedule.add_system_to_stage(FixedStage::Update, system)
+                });
+        }
+        self
+    }
+
+    fn add_fixed_system<Params>(&mut self, system: impl IntoSystemDescriptor<Params>) -> &mut Self {
+        self.add_fixed_systems(FixedSystems::default().with_system(system))
+    }
+
+    fn add_fixed_event<T: Event>(&mut self) -> &mut Self {
+        if !self.world.contains_resource::<Events<T>>() {
+            self.init_resource::<Events<T>>();
+            self.schedule
+                .stage(FixedStepStage, |schedule: &mut Schedule| {
+                    schedule.add_system_to_stage(
+                        FixedStage::First,
+                        Events::<T>::update_system.with_run_criteria(first_tick_of_frame),
+                    )
+                });
+        }
+        self
+    }
+}
@@ -1,135 +1,122 @@
-use crate::components::ScoreUi;
-use crate::main_menu::{ButtonActive, UIAssets};
-use crate::resources::PlayerScore;
+use crate::high_scores::HighScoreTable;
+use crate::menu::{MenuActionPlugin, MenuBuilder, MenuSelected, UIAssets};
+use crate::resources::{GameMode, PlayerScore};
+use crate::session::despawn_play_field;
 use crate::states::GameState;
+use crate::stats::{FinishRoundLabel, RunStats};
 
 use bevy::app::AppExit;
-use bevy::{prelude::*, ui::FocusPolicy};
+use bevy::prelude::*;
 
 pub struct GameOverMenuPlugin;
 
 impl Plugin for GameOverMenuPlugin {
     fn build(&self, app: &mut App) {
-        app.add_system_set(
-            SystemSet::on_enter(GameState::GameOver)
-                .with_system(setup_menu)
-                .with_system(despawn_all_non_ui),
-        )
-        .add_system_set(SystemSet::on_pause(GameState::GameOver).with_system(despawn_menu))
-        .add_system_set(SystemSet::on_update(GameState::GameOver).with_system(handle_quit_button));
+        app.add_plugin(MenuActionPlugin::<GameOverButton>::default())
+            .add_system_set(
+                SystemSet::on_enter(GameState::GameOver)
+                    .with_system(setup_menu.after(FinishRoundLabel))
+                    .with_system(despawn_play_field),
+            )
+            //Name entry for a new high score is pushed on top of the game over screen
+            .add_system_set(SystemSet::on_resume(GameState::GameOver).with_system(setup_menu))
+            .add_system_set(SystemSet::on_pause(GameState::GameOver).with_system(despawn_menu))
+            .add_system_set(SystemSet::on_exit(GameState::GameOver).with_system(despawn_menu))
+            .add_system_set(
+                SystemSet::on_update(GameState::GameOver).with_system(handle_game_over_buttons),
+            );
     }
 }
 
-fn despawn_menu(mut commands: Commands, button_query: Query<Entity, With<Button>>) {
-    for entity in button_query.iter() {
-        commands.entity(entity).despawn_recursive();
-    }
+#[derive(Component)]
+struct GameOverMenu;
+
+#[derive(Component, Clone, Copy)]
+enum GameOverButton {
+    PlayAgain,
+    MainMenu,
+    Quit,
 }
 
-fn exit_system(mut exit: EventWriter<AppExit>) {
-    exit.send(AppExit);
+fn despawn_menu(mut commands: Commands, menu_query: Query<Entity, With<GameOverMenu>>) {
+    for entity in menu_query.iter() {
+        commands.entity(entity).despawn_recursive();
+    }
 }
 
-fn handle_quit_button(
-    mut commands: Commands,
-    mut interaction_query: Query<
-        (&Children, &mut ButtonActive, &Interaction),
-        Changed<Interaction>,
-    >,
-    mut image_query: Query<&mut UiImage>,
+fn handle_game_over_buttons(
+    mut selected: EventReader<MenuSelected<GameOverButton>>,
     mut state: ResMut<State<GameState>>,
     mut app_exit: EventWriter<AppExit>,
-    ui_assets: Res<UIAssets>,
 ) {
-    for (children, mut active, interaction) in interaction_query.iter_mut() {
-        let child = children.iter().next().unwrap();
-        let mut image = image_query.get_mut(*child).unwrap();
-
-        match interaction {
-            Interaction::Clicked => {
-                if active.0 {
-                    image.0 = ui_assets.button_pressed.clone();
-                    app_exit.send(AppExit)
-                }
+    //Both of these unwind the stack through MainGame's exit, which resets the session
+    for MenuSelected(action) in selected.iter() {
+        match action {
+            GameOverButton::PlayAgain => {
+                let _ = state.replace(GameState::MainGame);
             }
-            Interaction::Hovered | Interaction::None => {
-                image.0 = ui_assets.button.clone();
+            GameOverButton::MainMenu => {
+                let _ = state.replace(GameState::MainMenu);
             }
+            GameOverButton::Quit => app_exit.send(AppExit),
         }
     }
 }
 
 fn setup_menu(
     mut commands: Commands,
-    assets: Res<AssetServer>,
+    ui_assets: Res<UIAssets>,
     score: Res<PlayerScore>,
-    mut query: Query<&mut Text, With<ScoreUi>>,
+    game_mode: Res<GameMode>,
+    high_scores: Res<HighScoreTable>,
+    run_stats: Res<RunStats>,
 ) {
-    let ui_assets = UIAssets {
-        font: assets.load("FiraSans-Bold.ttf"),
-        button: assets.load("button.png"),
-        button_pressed: assets.load("button_pressed.png"),
-    };
+    let best = high_scores.best(*game_mode).unwrap_or(0).max(score.0);
+    let seconds = run_stats.seconds() as u32;
+    let summary = [
+        format!("Rounds: {}", run_stats.rounds.len()),
+        format!(
+            "Kills: {} ({} power shot)",
+            run_stats.total(|round| round.kills),
+            run_stats.total(|round| round.power_shot_kills)
+        ),
+        format!("Accuracy: {:.0}%", run_stats.accuracy() * 100.),
+        format!(
+            "Civilians Saved: {}  Lost: {}",
+            run_stats.total(|round| round.civilians_saved),
+            run_stats.total(|round| round.civilians_lost)
+        ),
+        format!("Overheats: {}", run_stats.total(|round| round.overheats)),
+        format!("Lives Lost: {}", run_stats.total(|round| round.lives_lost)),
+        format!("Time: {}:{:02}", seconds / 60, seconds % 60),
+    ];
 
-    commands.spawn_bundle(Camera2dBundle::default());
     commands
-        .spawn_bundle(ButtonBundle {
-            style: Style {
-                align_self: AlignSelf::Center,
-                align_items: AlignItems::Center,
-                justify_content: JustifyContent::Center,
-                size: Size::new(Val::Percent(20.0), Val::Percent(10.0)),
-                position: UiRect::new(
-                    Val::Percent(40.0),
-                    Val::Percent(0.0),
-                    Val::Percent(0.0),
-                    Val::Percent(0.0),
-                ),
-                ..Default::default()
-            },
-            color: Color::NONE.into(),
-            ..Default::default()
-        })
-        .insert(ButtonActive(true))
-        .with_children(|parent| {
-            parent
-                .spawn_bundle(ImageBundle {
-                    style: Style {
-                        size: Size::new(Val::Percent(100.0), Val::Percent(100.0)),
-                        justify_content: JustifyContent::Center,
-                        align_items: AlignItems::Center,
-                        ..Default::default()
-                    },
-                    image: ui_assets.button.clone().into(),
-                    ..Default::default()
-                })
-                .insert(FocusPolicy::Pass)
-                .with_children(|parent| {
-                    parent.spawn_bundle(TextBundle {
-                        text: Text::from_section(
-                            "Quit Game",
-                            TextStyle {
-                                font: ui_assets.font.clone(),
-                                font_size: 40.0,
-                                color: Color::rgb(0.9, 0.9, 0.9),
-                            },
-                        ),
-                        focus_policy: FocusPolicy::Pass,
-                        ..Default::default()
-                    });
-                });
-        });
-    commands.insert_resource(ui_assets);
-}
-
-fn despawn_all_non_ui(mut commands: Commands, query: Query<Entity, Without<ScoreUi>>) {
-    for entity in query.iter() {
-        commands.entity(entity).despawn_recursive();
-    }
-}
-
-fn despawn_all(mut commands: Commands, query: Query<Entity>) {
-    for entity in query.iter() {
-        commands.entity(entity).despawn_recursive();
-    }
+        .spawn_bundle(
+            TextBundle::from_section(
+                summary.join("\n"),
+                TextStyle {
+                    font: ui_assets.font.clone(),
+                    font_size: 24.0,
+                    color: Color::rgb(0.9, 0.9, 0.9),
+                },
+            )
+            .with_style(Style {
+                position_type: PositionType::Absolute,
+                position: UiRect {
+                    left: Val::Percent(5.0),
+                    top: Val::Percent(40.0),
+                    ..default()
+                },
+                ..default()
+            }),
+        )
+        .insert(GameOverMenu);
+    MenuBuilder::new()
+        .title(format!("Best ({}): {}", game_mode.name(), best))
+        .button("Play Again", GameOverButton::PlayAgain)
+        .button("Main Menu", GameOverButton::MainMenu)
+        .button("Quit Game", GameOverButton::Quit)
+        .spawn(&mut commands, &ui_assets)
+        .insert(GameOverMenu);
 }
@@ -1,14 +1,12 @@
 use bevy::prelude::*;
 
 use crate::resources::Controller;
-use crate::states::GameState;
 pub struct GamepadPlugin;
 
 impl Plugin for GamepadPlugin {
     fn build(&self, app: &mut App) {
-        app.add_system_set(
-            SystemSet::on_update(GameState::MainGame).with_system(gamepad_connection_system),
-        );
+        //Menus use the controller as well, so connections are tracked in every state
+        app.add_system(gamepad_connection_system);
     }
 }
 
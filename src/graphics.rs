@@ -1,47 +1,334 @@
-use bevy::{
-    ecs::query::{WorldQuery, WorldQueryGats},
-    prelude::*,
-    reflect::{TypeData, TypeInfo},
-};
-use bevy_rapier2d::prelude::*;
-
-use crate::{
-    components::{AnimationTimer, Direction},
-    states::{CivilianAnimationInfo, EnemyAnimationInfo, PlayerAnimationInfo, SpriteLocation},
-};
+use std::collections::HashMap;
+use std::fs;
+use std::path::{Path, PathBuf};
+use std::sync::Arc;
 
+use bevy::prelude::*;
+use serde::Deserialize;
+
+use crate::fixed_step::{FixedStepApp, FixedTime};
+use crate::pause::not_paused;
+
+//Sprite sheets are described by the .ron files in assets/sprites, one per character, and loaded once at
+//startup. A character gets an Animator for its sheet and plays animations on it by name. Descriptors can
+//name events on frames, which come out as AnimationEvents for gameplay systems to react to
 pub struct AnimationPlugin;
 
 impl Plugin for AnimationPlugin {
     fn build(&self, app: &mut App) {
-        app.add_system(animation_system::<PlayerAnimationInfo>);
-        app.add_system(animation_system::<EnemyAnimationInfo>);
-        app.add_system(animation_system::<CivilianAnimationInfo>);
+        app.add_fixed_event::<AnimationEvent>()
+            .add_startup_system(load_sprite_sheets)
+            .add_fixed_system(animation_system.with_run_criteria(not_paused));
+    }
+}
+
+pub struct AnimationEvent {
+    pub entity: Entity,
+    pub name: String,
+}
+
+const SPRITE_SHEET_DIR: &str = "assets/sprites";
+
+#[derive(Deserialize)]
+enum FrameDescriptor {
+    Row { row: usize, count: usize },
+    List(Vec<usize>),
+}
+
+impl FrameDescriptor {
+    //Indices into the atlas, which counts along each row before moving down to the next
+    fn indices(&self, columns: usize) -> Vec<usize> {
+        match self {
+            FrameDescriptor::Row { row, count } => {
+                (0..*count).map(|frame| row * columns + frame).collect()
+            }
+            FrameDescriptor::List(frames) => frames.clone(),
+        }
+    }
+}
+
+fn default_frame_seconds() -> f32 {
+    0.1
+}
+
+fn default_true() -> bool {
+    true
+}
+
+#[derive(Deserialize)]
+struct AnimationDescriptor {
+    frames: FrameDescriptor,
+    #[serde(default = "default_frame_seconds")]
+    frame_seconds: f32,
+    #[serde(default = "default_true")]
+    looping: bool,
+    #[serde(default = "default_true")]
+    flip: bool,
+    //Only for animations that don't loop, otherwise they hold their last frame
+    #[serde(default)]
+    then: Option<String>,
+    #[serde(default)]
+    events: HashMap<usize, String>,
+    //Sent once the last frame of an animation that doesn't loop has had its full time on screen
+    #[serde(default)]
+    on_finish: Option<String>,
+}
+
+#[derive(Deserialize)]
+struct SpriteSheetDescriptor {
+    texture: String,
+    tile_size: (f32, f32),
+    columns: usize,
+    rows: usize,
+    default_animation: String,
+    animations: HashMap<String, AnimationDescriptor>,
+}
+
+struct Animation {
+    frames: Vec<usize>,
+    frame_seconds: f32,
+    looping: bool,
+    flip: bool,
+    then: Option<String>,
+    events: HashMap<usize, String>,
+    on_finish: Option<String>,
+}
+
+pub struct SpriteSheet {
+    pub atlas: Handle<TextureAtlas>,
+    default_animation: String,
+    animations: HashMap<String, Animation>,
+}
+
+//Sheets by file name, so assets/sprites/crab.ron is "crab"
+pub struct SpriteSheets(HashMap<String, Arc<SpriteSheet>>);
+
+impl SpriteSheets {
+    pub fn get(&self, name: &str) -> &Arc<SpriteSheet> {
+        self.0
+            .get(name)
+            .unwrap_or_else(|| panic!("No sprite sheet called {} in {}", name, SPRITE_SHEET_DIR))
+    }
+}
+
+fn read_descriptor(path: &Path) -> Result<SpriteSheetDescriptor, Box<dyn std::error::Error>> {
+    let contents = fs::read_to_string(path)?;
+    Ok(ron::from_str(&contents)?)
+}
+
+fn build_sprite_sheet(
+    descriptor: SpriteSheetDescriptor,
+    asset_server: &AssetServer,
+    texture_atlases: &mut Assets<TextureAtlas>,
+) -> SpriteSheet {
+    let texture = asset_server.load(&descriptor.texture);
+    let atlas = TextureAtlas::from_grid(
+        texture,
+        Vec2::new(descriptor.tile_size.0, descriptor.tile_size.1),
+        descriptor.columns,
+        descriptor.rows,
+    );
+    let animations = descriptor
+        .animations
+        .into_iter()
+        .map(|(name, animation)| {
+            let animation = Animation {
+                frames: animation.frames.indices(descriptor.columns),
+                frame_seconds: animation.frame_seconds,
+                looping: animation.looping,
+                flip: animation.flip,
+                then: animation.then,
+                events: animation.events,
+                on_finish: animation.on_finish,
+            };
+            (name, animation)
+        })
+        .collect();
+
+    SpriteSheet {
+        atlas: texture_atlases.add(atlas),
+        default_animation: descriptor.default_animation,
+        animations,
+    }
+}
+
+//A broken descriptor is a bug in the assets, so fail loudly rather than spawning invisible characters
+fn load_sprite_sheets(
+    mut commands: Commands,
+    asset_server: Res<AssetServer>,
+    mut texture_atlases: ResMut<Assets<TextureAtlas>>,
+) {
+    let paths: Vec<PathBuf> = fs::read_dir(SPRITE_SHEET_DIR)
+        .unwrap_or_else(|err| panic!("Couldn't read {}: {}", SPRITE_SHEET_DIR, err))
+        .filter_map(|entry| entry.ok().map(|entry| entry.path()))
+        .filter(|path| path.extension().is_some_and(|extension| extension == "ron"))
+        .collect();
+
+    let mut sheets = HashMap::new();
+    for path in paths {
+        let descriptor = read_descriptor(&path)
+            .unwrap_or_else(|err| panic!("Couldn't load {}: {}", path.display(), err));
+        if !descriptor
+            .animations
+            .contains_key(&descriptor.default_animation)
+        {
+            panic!(
+                "{} has no {} animation to start with",
+                path.display(),
+                descriptor.default_animation
+            );
+        }
+        for (name, animation) in descriptor.animations.iter() {
+            let chained = animation.then.as_ref();
+            if let Some(next) = chained.filter(|next| !descriptor.animations.contains_key(*next)) {
+                panic!("{} chains {} into missing {}", path.display(), name, next);
+            }
+            let frame_count = descriptor.columns * descriptor.rows;
+            let indices = animation.frames.indices(descriptor.columns);
+            if let Some(frame) = indices.iter().find(|frame| **frame >= frame_count) {
+                panic!(
+                    "{} uses frame {} in {} but its {}x{} sheet only goes up to {}",
+                    path.display(),
+                    frame,
+                    name,
+                    descriptor.columns,
+                    descriptor.rows,
+                    frame_count - 1
+                );
+            }
+        }
+        let name = path.file_stem().unwrap().to_string_lossy().into_owned();
+        let sheet = build_sprite_sheet(descriptor, &asset_server, &mut texture_atlases);
+        sheets.insert(name, Arc::new(sheet));
+    }
+    commands.insert_resource(SpriteSheets(sheets));
+}
+
+#[derive(Component)]
+pub struct Animator {
+    sheet: Arc<SpriteSheet>,
+    current: String,
+    frame: usize,
+    timer: Timer,
+    //The frame whose events have been sent, so each frame only sends them once per pass
+    shown: Option<usize>,
+    finished: bool,
+    pub facing_left: bool,
+}
+
+impl Animator {
+    pub fn new(sheet: &Arc<SpriteSheet>) -> Self {
+        let current = sheet.default_animation.clone();
+        let frame_seconds = sheet.animations[&current].frame_seconds;
+        Self {
+            sheet: sheet.clone(),
+            current,
+            frame: 0,
+            timer: Timer::from_seconds(frame_seconds, true),
+            shown: None,
+            finished: false,
+            facing_left: false,
+        }
+    }
+
+    //Starts the animation from its first frame, unless it's already the one playing
+    pub fn play(&mut self, name: &str) {
+        if self.current == name {
+            return;
+        }
+        match self.sheet.animations.get(name) {
+            Some(animation) => {
+                self.current = name.to_string();
+                self.frame = 0;
+                self.timer = Timer::from_seconds(animation.frame_seconds, true);
+                self.shown = None;
+                self.finished = false;
+            }
+            None => warn!("Sprite sheet has no {} animation", name),
+        }
+    }
+
+    pub fn current(&self) -> &str {
+        &self.current
+    }
+
+    //True once an animation that doesn't loop or chain is holding its last frame
+    pub fn is_finished(&self) -> bool {
+        self.finished
+    }
+
+    fn animation(&self) -> &Animation {
+        &self.sheet.animations[&self.current]
+    }
+
+    fn frame_events(&mut self) -> Option<&String> {
+        if self.shown == Some(self.frame) {
+            return None;
+        }
+        self.shown = Some(self.frame);
+        self.sheet.animations[&self.current].events.get(&self.frame)
+    }
+
+    //Moves on a frame, returning the finish event if that ran a one shot animation out
+    fn step(&mut self) -> Option<String> {
+        let animation = &self.sheet.animations[&self.current];
+        if self.frame + 1 < animation.frames.len() {
+            self.frame += 1;
+            return None;
+        }
+        if animation.looping {
+            self.frame = 0;
+            self.shown = None;
+            return None;
+        }
+        if self.finished {
+            return None;
+        }
+
+        let on_finish = animation.on_finish.clone();
+        match animation.then.clone() {
+            Some(next) => self.play(&next),
+            None => self.finished = true,
+        }
+        on_finish
     }
 }
 
-fn animation_system<T: Component + SpriteLocation>(
-    time: Res<Time>,
-    texture_atlases: Res<Assets<TextureAtlas>>,
-    mut query: Query<(
-        &mut AnimationTimer,
-        &mut TextureAtlasSprite,
-        &Handle<TextureAtlas>,
-        &T,
-    )>,
+//Frames advance on the animator's own timer, sending the events named for each frame as it comes up
+fn animation_system(
+    time: Res<FixedTime>,
+    mut send_animation_event: EventWriter<AnimationEvent>,
+    mut query: Query<(Entity, &mut Animator, &mut TextureAtlasSprite)>,
 ) {
-    for (mut timer, mut sprite, texture_atlas_handle, info) in query.iter_mut() {
-        timer.tick(time.delta());
-        if timer.just_finished() {
-            let texture_atlas = texture_atlases.get(texture_atlas_handle).unwrap();
-            sprite.index = info.next_index(sprite.index)
+    for (entity, mut animator, mut sprite) in query.iter_mut() {
+        animator.timer.tick(time.delta());
+        let steps = animator.timer.times_finished_this_tick();
+        let mut send = |name: &String| {
+            send_animation_event.send(AnimationEvent {
+                entity,
+                name: name.clone(),
+            })
+        };
+
+        if let Some(name) = animator.frame_events() {
+            send(name);
+        }
+        for _ in 0..steps {
+            if animator.finished {
+                break;
+            }
+            if let Some(name) = animator.step() {
+                send(&name);
+            }
+            if let Some(name) = animator.frame_events() {
+                send(name);
+            }
         }
 
-        //This may be a naive approach but it might jsut work for what we need, and it would be tedious to create 2 copies of every sprite
-        if info.is_flip() {
-            sprite.flip_x = true;
-        } else {
-            sprite.flip_x = false;
+        let animation = animator.animation();
+        if let Some(index) = animation.frames.get(animator.frame) {
+            sprite.index = *index;
         }
+        sprite.flip_x = animator.facing_left && animation.flip;
     }
 }
@@ -0,0 +1,427 @@
+use std::error::Error;
+use std::fs;
+use std::path::{Path, PathBuf};
+use std::time::Instant;
+
+use bevy::app::AppExit;
+use bevy::asset::AssetPlugin;
+use bevy::hierarchy::HierarchyPlugin;
+use bevy::input::InputPlugin;
+use bevy::prelude::*;
+use bevy::time::{create_time_channels, TimeSender};
+use bevy::transform::TransformPlugin;
+use serde::Deserialize;
+
+use crate::components::{Dead, Enemy, Player};
+use crate::controls::{PlayerInput, ReadInputLabel};
+use crate::fixed_step::{FixedStepApp, FixedStepPlugin, FixedSystems, FixedTime};
+use crate::menu::MenuPlugin;
+use crate::particles::EffectEvent;
+use crate::replay::{
+    start_queued_replay_now_system, QueuedReplay, Replay, ReplayPlugin, ReplayRecorder,
+};
+use crate::resources::{GameMode, GameRng, PlayerScore, WindowSize};
+use crate::rounds::{new_run_seed, seed_run, RoundTracker};
+use crate::settings::Settings;
+use crate::sound::SoundEvent;
+use crate::states::GameState;
+use crate::stats::{FinishRoundLabel, RoundStats, RunStats};
+use crate::{setup_system, GameplayPlugins};
+
+//Plays the game without a window, for balance sims and anything else that wants whole rounds played
+//out by a script: `my-blaster-runs-hot --headless sims/turret.ron`. The clock is simulated, each update
+//moves it on by TIME_STEP, so a run goes as fast as the machine allows and doesn't depend on it. Given
+//a replay, the replay's run is played out instead of the script's input
+pub fn run(script_path: &Path, replay: Option<Replay>) {
+    let script = match Script::load(script_path) {
+        Ok(script) => script,
+        Err(err) => {
+            eprintln!("Couldn't load {}: {}", script_path.display(), err);
+            std::process::exit(1);
+        }
+    };
+    build_app(script, replay).run();
+}
+
+//Everything up to running it, so tests can step through a run an update at a time
+fn build_app(script: Script, replay: Option<Replay>) -> App {
+    //Default settings rather than the player's, so a sim plays out the same on every machine
+    let settings = Settings::default();
+    let (time_sender, time_receiver) = create_time_channels();
+    let recorder = ReplayRecorder::new(script.record.clone());
+
+    let mut app = App::new();
+    app.insert_resource(WindowSize {
+        w: settings.resolution.0,
+        h: settings.resolution.1,
+    })
+    .insert_resource(settings)
+    .insert_resource(time_receiver)
+    .insert_resource(SimulatedClock {
+        sender: time_sender,
+        started: Instant::now(),
+    })
+    .add_plugins(MinimalPlugins)
+    .add_plugin(TransformPlugin)
+    .add_plugin(HierarchyPlugin)
+    .add_plugin(InputPlugin)
+    .add_plugin(AssetPlugin)
+    .add_asset::<Image>()
+    .add_asset::<TextureAtlas>()
+    .add_plugin(FixedStepPlugin)
+    .add_state(GameState::MainGame)
+    //Normally registered by the particles and the sound, which aren't here
+    .add_event::<EffectEvent>()
+    .add_event::<SoundEvent>()
+    //The intermission screen is built from the menu pieces
+    .add_plugin(MenuPlugin)
+    //Ahead of the game so the scripted input is in before the player acts on it
+    .add_plugin(HeadlessPlugin { script })
+    .add_plugin(ReplayPlugin)
+    //Only kept if the script asks for it, not over the player's last run
+    .insert_resource(recorder)
+    .insert_resource(QueuedReplay(replay))
+    .add_plugins(GameplayPlugins)
+    .add_startup_system(setup_system);
+    app
+}
+
+struct HeadlessPlugin {
+    script: Script,
+}
+
+impl Plugin for HeadlessPlugin {
+    fn build(&self, app: &mut App) {
+        app.insert_resource(self.script.clone())
+            .add_startup_system_to_stage(StartupStage::PostStartup, start_run_system)
+            //After the script has set the run up, so a replay's mode and seed win
+            .add_startup_system_to_stage(
+                StartupStage::PostStartup,
+                start_queued_replay_now_system.after(start_run_system),
+            )
+            .add_fixed_systems(
+                FixedSystems::on_update(GameState::MainGame)
+                    .with_system(scripted_input_system.label(ReadInputLabel)),
+            )
+            .add_system_set(
+                SystemSet::on_enter(GameState::Intermission)
+                    .with_system(report_round_system.after(FinishRoundLabel)),
+            )
+            .add_system_set(
+                SystemSet::on_enter(GameState::GameOver)
+                    .with_system(report_round_system.after(FinishRoundLabel))
+                    .with_system(game_over_system.after(report_round_system)),
+            )
+            .add_system(time_limit_system)
+            .add_system_to_stage(CoreStage::Last, advance_clock_system);
+    }
+}
+
+#[derive(Clone, Deserialize)]
+enum ScriptedAim {
+    //A fixed direction
+    Fixed(f32, f32),
+    //Straight at the closest living enemy, holding fire while there isn't one
+    NearestEnemy,
+}
+
+impl Default for ScriptedAim {
+    fn default() -> Self {
+        ScriptedAim::Fixed(1.0, 0.0)
+    }
+}
+
+#[derive(Clone, Deserialize)]
+struct ScriptStep {
+    //Seconds into the run (or into the loop) this step starts, it lasts until the next one does
+    at: f32,
+    #[serde(default)]
+    movement: (f32, f32),
+    #[serde(default)]
+    aim: ScriptedAim,
+    #[serde(default)]
+    firing: bool,
+}
+
+#[derive(Clone, Deserialize)]
+struct Script {
+    mode: GameMode,
+    //Without one every run is different, with one a run plays out exactly the same every time
+    #[serde(default)]
+    seed: Option<u64>,
+    //Stops the run if the game isn't over by then
+    max_seconds: f32,
+    //The steps start over after this many seconds, without it the last step holds to the end
+    #[serde(default)]
+    loop_seconds: Option<f32>,
+    //Where to save a replay of the run, to watch it with --replay
+    #[serde(default)]
+    record: Option<PathBuf>,
+    steps: Vec<ScriptStep>,
+}
+
+impl Script {
+    fn load(path: &Path) -> Result<Self, Box<dyn Error>> {
+        let contents = fs::read_to_string(path)?;
+        let script: Script = ron::from_str(&contents)?;
+        if script.steps.is_empty() {
+            return Err("the script has no steps".into());
+        }
+        Ok(script)
+    }
+
+    fn step_at(&self, seconds: f32) -> &ScriptStep {
+        let seconds = match self.loop_seconds {
+            Some(loop_seconds) if loop_seconds > 0.0 => seconds % loop_seconds,
+            _ => seconds,
+        };
+        self.steps
+            .iter()
+            .take_while(|step| step.at <= seconds)
+            .last()
+            .unwrap_or(&self.steps[0])
+    }
+}
+
+//Feeds the simulated time to bevy's time system in place of the real clock
+struct SimulatedClock {
+    sender: TimeSender,
+    //Real time, just for the report at the end
+    started: Instant,
+}
+
+//Runs at the very end of each update, ready for the time system at the start of the next. Exactly one
+//step on from the last update, so every update is exactly one gameplay tick
+fn advance_clock_system(time: Res<Time>, clock: Res<SimulatedClock>) {
+    let now = time.last_update().unwrap_or_else(|| time.startup()) + FixedTime::step();
+    let _ = clock.sender.0.try_send(now);
+}
+
+fn start_run_system(
+    script: Res<Script>,
+    mut game_mode: ResMut<GameMode>,
+    mut round_tracker: ResMut<RoundTracker>,
+    mut game_rng: ResMut<GameRng>,
+) {
+    *game_mode = script.mode;
+    let seed = script.seed.unwrap_or_else(new_run_seed);
+    seed_run(seed, script.mode, &mut round_tracker, &mut game_rng);
+}
+
+fn scripted_input_system(
+    time: Res<FixedTime>,
+    script: Res<Script>,
+    mut input: ResMut<PlayerInput>,
+    player_query: Query<&Transform, With<Player>>,
+    enemy_query: Query<&Transform, (With<Enemy>, Without<Dead>)>,
+) {
+    let step = script.step_at(time.seconds_since_startup() as f32);
+    input.movement = Vec2::new(step.movement.0, step.movement.1);
+    input.firing = step.firing;
+    input.aim = match step.aim {
+        ScriptedAim::Fixed(x, y) => Vec2::new(x, y),
+        ScriptedAim::NearestEnemy => {
+            let player = player_query
+                .get_single()
+                .map(|transform| transform.translation.truncate())
+                .unwrap_or_default();
+            let nearest = enemy_query
+                .iter()
+                .map(|transform| transform.translation.truncate() - player)
+                .min_by(|a, b| a.length_squared().total_cmp(&b.length_squared()));
+            if nearest.is_none() {
+                input.firing = false;
+            }
+            nearest.unwrap_or_default()
+        }
+    };
+}
+
+fn report_round_system(run_stats: Res<RunStats>) {
+    if let Some(round) = run_stats.rounds.last() {
+        println!(
+            "Round {}: {} kills, {:.0}% accuracy, {} civilians saved, {} lost, {} overheats, {} lives lost, {:.1}s, +{} points",
+            round.round,
+            round.kills,
+            round.accuracy() * 100.,
+            round.civilians_saved,
+            round.civilians_lost,
+            round.overheats,
+            round.lives_lost,
+            round.seconds,
+            round.score_gained,
+        );
+    }
+}
+
+fn report_run(clock: &SimulatedClock, fixed_time: &FixedTime, run_stats: &RunStats, outcome: &str) {
+    println!(
+        "{} after {:.1}s of play ({} ticks) in {:.2}s: {} rounds, {} points, {} kills, {:.0}% accuracy, seed {}",
+        outcome,
+        fixed_time.seconds_since_startup(),
+        fixed_time.ticks(),
+        clock.started.elapsed().as_secs_f32(),
+        run_stats.rounds.len(),
+        run_stats.final_score,
+        run_stats.total(|round| round.kills),
+        run_stats.accuracy() * 100.,
+        run_stats.seed,
+    );
+}
+
+fn game_over_system(
+    clock: Res<SimulatedClock>,
+    fixed_time: Res<FixedTime>,
+    run_stats: Res<RunStats>,
+    player_query: Query<(), (With<Player>, With<Dead>)>,
+    mut app_exit: EventWriter<AppExit>,
+) {
+    let outcome = if player_query.is_empty() {
+        "Won"
+    } else {
+        "Died"
+    };
+    report_run(&clock, &fixed_time, &run_stats, outcome);
+    app_exit.send(AppExit);
+}
+
+//The round that's still going is counted as it stands
+fn time_limit_system(
+    clock: Res<SimulatedClock>,
+    fixed_time: Res<FixedTime>,
+    script: Res<Script>,
+    round_stats: Res<RoundStats>,
+    score: Res<PlayerScore>,
+    mut run_stats: ResMut<RunStats>,
+    mut app_exit: EventWriter<AppExit>,
+) {
+    if fixed_time.seconds_since_startup() < script.max_seconds as f64 {
+        return;
+    }
+    let mut round = round_stats.clone();
+    round.score_gained = round.score_gained(&score);
+    run_stats.rounds.push(round);
+    run_stats.final_score = score.0;
+    report_run(&clock, &fixed_time, &run_stats, "Out of time");
+    app_exit.send(AppExit);
+}
+
+#[cfg(test)]
+mod tests {
+    use bevy::ecs::event::{Events, ManualEventReader};
+
+    use super::*;
+    use crate::components::{Civilian, Lives};
+    use crate::projectile_collision::LivingBeingHitEvent;
+
+    //Updates until the run is over or `done` says so, one tick per update
+    fn play(mut app: App, max_updates: u32, done: impl Fn(&World) -> bool) -> App {
+        let mut exit_reader = ManualEventReader::<AppExit>::default();
+        for _ in 0..max_updates {
+            app.update();
+            let exited = exit_reader
+                .iter(app.world.resource::<Events<AppExit>>())
+                .next()
+                .is_some();
+            if exited || done(&app.world) {
+                break;
+            }
+        }
+        app
+    }
+
+    fn turret_script(seed: u64) -> Script {
+        let mut script = Script::load(Path::new("sims/turret.ron")).unwrap();
+        script.seed = Some(seed);
+        script
+    }
+
+    #[test]
+    fn turret_clears_a_round() {
+        let app = play(build_app(turret_script(3), None), 60 * 60, |world| {
+            !world.resource::<RunStats>().rounds.is_empty()
+        });
+        let run_stats = app.world.resource::<RunStats>();
+        assert_eq!(run_stats.rounds.len(), 1, "the first round never finished");
+        let round = &run_stats.rounds[0];
+        assert!(round.shots_fired > 0);
+        assert!(round.kills > 0);
+        assert!(round.score_gained > 0);
+    }
+
+    //Every stat of the run, so any difference between two runs shows
+    fn finish_run(script: Script) -> (String, usize) {
+        let app = play(build_app(script, None), 60 * 120, |_| false);
+        let run_stats = app.world.resource::<RunStats>();
+        assert!(
+            !run_stats.rounds.is_empty(),
+            "the run never finished a round"
+        );
+        (
+            serde_json::to_string(run_stats).unwrap(),
+            app.world.resource::<PlayerScore>().0,
+        )
+    }
+
+    #[test]
+    fn same_seed_and_input_play_out_the_same() {
+        let first = finish_run(turret_script(3));
+        let second = finish_run(turret_script(3));
+        assert_eq!(first, second);
+    }
+
+    #[test]
+    fn civilians_left_when_the_last_life_goes_are_lost() {
+        let mut app = build_app(turret_script(3), None);
+        let mut civilians = app.world.query_filtered::<(), With<Civilian>>();
+        while civilians.iter(&app.world).next().is_none() {
+            app.update();
+        }
+
+        //Finish the player off on their last life before the civilian can reach them
+        let mut players = app
+            .world
+            .query_filtered::<(Entity, &mut Lives), With<Player>>();
+        let (player, mut lives) = players.single_mut(&mut app.world);
+        lives.lives_num = 0;
+        app.world
+            .resource_mut::<Events<LivingBeingHitEvent>>()
+            .send(LivingBeingHitEvent {
+                entity: player,
+                damage: crate::constants::PLAYER_HEALTH,
+                power_shot: false,
+                position: Vec2::ZERO,
+            });
+
+        let app = play(app, 60 * 10, |_| false);
+        let run_stats = app.world.resource::<RunStats>();
+        let round = run_stats.rounds.last().expect("the run never ended");
+        assert!(round.civilians_lost > 0);
+        assert_eq!(round.civilians_saved, 0);
+    }
+
+    //The turret run with the window made bigger part way through, like a player changing resolution
+    fn resized_run(record: &Path) -> String {
+        let mut script = turret_script(3);
+        script.record = Some(record.to_path_buf());
+        let mut app = build_app(script, None);
+        for _ in 0..300 {
+            app.update();
+        }
+        *app.world.resource_mut::<WindowSize>() = WindowSize { w: 1600., h: 900. };
+        let app = play(app, 60 * 120, |_| false);
+        serde_json::to_string(app.world.resource::<RunStats>()).unwrap()
+    }
+
+    #[test]
+    fn replays_follow_window_resizes() {
+        let path = std::env::temp_dir().join("mbrh_resized_run.replay");
+        let recorded = resized_run(&path);
+        let replay = Replay::load(&path).unwrap();
+        let app = play(build_app(turret_script(3), Some(replay)), 60 * 120, |_| {
+            false
+        });
+        let played_back = serde_json::to_string(app.world.resource::<RunStats>()).unwrap();
+        assert_eq!(recorded, played_back);
+    }
+}
@@ -0,0 +1,515 @@
+use std::error::Error;
+use std::fs;
+use std::path::{Path, PathBuf};
+
+use bevy::ecs::system::EntityCommands;
+use bevy::prelude::*;
+use serde::{Deserialize, Serialize};
+
+use crate::constants::{MAX_HIGH_SCORES, MAX_NAME_LENGTH};
+use crate::menu::{MenuActionPlugin, MenuBuilder, MenuSelected, UIAssets};
+use crate::replay::ReplayPlayback;
+use crate::resources::{Controller, GameMode, PlayerScore};
+use crate::rounds::RoundTracker;
+use crate::states::GameState;
+
+const NAME_CHARACTERS: &[u8] = b"ABCDEFGHIJKLMNOPQRSTUVWXYZ0123456789 ";
+const DEFAULT_NAME: &str = "QWARK";
+
+pub struct HighScorePlugin;
+
+impl Plugin for HighScorePlugin {
+    fn build(&self, app: &mut App) {
+        app.add_plugin(MenuActionPlugin::<HighScoreButton>::default())
+            .insert_resource(HighScoreTable::load(HighScoreTable::default_path()))
+            .insert_resource(PendingHighScore(None))
+            .add_system_set(SystemSet::on_enter(GameState::GameOver).with_system(check_high_score))
+            .add_system_set(
+                SystemSet::on_update(GameState::GameOver).with_system(prompt_name_entry),
+            )
+            .add_system_set(SystemSet::on_enter(GameState::NameEntry).with_system(setup_name_entry))
+            .add_system_set(
+                SystemSet::on_update(GameState::NameEntry)
+                    .with_system(name_entry_input)
+                    .with_system(update_name_entry_text)
+                    .with_system(confirm_name_entry),
+            )
+            .add_system_set(
+                SystemSet::on_exit(GameState::NameEntry).with_system(despawn_high_score_ui),
+            )
+            .add_system_set(
+                SystemSet::on_enter(GameState::HighScores).with_system(setup_high_score_screen),
+            )
+            .add_system_set(
+                SystemSet::on_update(GameState::HighScores)
+                    .with_system(high_score_screen_input)
+                    .with_system(handle_high_score_buttons)
+                    .with_system(update_high_score_list),
+            )
+            .add_system_set(
+                SystemSet::on_exit(GameState::HighScores).with_system(despawn_high_score_ui),
+            );
+    }
+}
+
+#[derive(Clone, Debug, Serialize, Deserialize)]
+pub struct HighScoreEntry {
+    pub name: String,
+    pub score: usize,
+    pub mode: GameMode,
+    pub round: u32,
+}
+
+pub struct HighScoreTable {
+    path: PathBuf,
+    entries: Vec<HighScoreEntry>,
+}
+
+impl HighScoreTable {
+    pub fn default_path() -> PathBuf {
+        dirs::data_dir()
+            .unwrap_or_else(|| PathBuf::from("."))
+            .join("my-blaster-runs-hot")
+            .join("high_scores.csv")
+    }
+
+    //A missing file is a fresh install, a broken one gets moved aside so the player can still save new scores
+    pub fn load(path: PathBuf) -> Self {
+        let mut table = HighScoreTable {
+            path,
+            entries: Vec::new(),
+        };
+        if !table.path.exists() {
+            return table;
+        }
+
+        match read_entries(&table.path) {
+            Ok(entries) => {
+                for entry in entries {
+                    table.insert(entry);
+                }
+            }
+            Err(err) => {
+                eprintln!(
+                    "High score file {} is corrupted ({}), starting a new table",
+                    table.path.display(),
+                    err
+                );
+                let _ = fs::rename(&table.path, table.path.with_extension("csv.bak"));
+            }
+        }
+        table
+    }
+
+    pub fn save(&self) -> Result<(), Box<dyn Error>> {
+        if let Some(parent) = self.path.parent() {
+            fs::create_dir_all(parent)?;
+        }
+        //Written next to the real file first so a crash mid-save can't corrupt the table
+        let temp_path = self.path.with_extension("csv.tmp");
+        let mut writer = csv::Writer::from_path(&temp_path)?;
+        for entry in self.entries.iter() {
+            writer.serialize(entry)?;
+        }
+        writer.flush()?;
+        fs::rename(&temp_path, &self.path)?;
+        Ok(())
+    }
+
+    pub fn for_mode(&self, mode: GameMode) -> impl Iterator<Item = &HighScoreEntry> {
+        self.entries.iter().filter(move |entry| entry.mode == mode)
+    }
+
+    pub fn best(&self, mode: GameMode) -> Option<usize> {
+        self.for_mode(mode).map(|entry| entry.score).max()
+    }
+
+    pub fn qualifies(&self, mode: GameMode, score: usize) -> bool {
+        if score == 0 {
+            return false;
+        }
+        let scores: Vec<usize> = self.for_mode(mode).map(|entry| entry.score).collect();
+        scores.len() < MAX_HIGH_SCORES || scores.iter().any(|best| score > *best)
+    }
+
+    pub fn insert(&mut self, mut entry: HighScoreEntry) {
+        entry.name.truncate(MAX_NAME_LENGTH);
+        let mode = entry.mode;
+        self.entries.push(entry);
+        self.entries
+            .sort_by_key(|entry| std::cmp::Reverse(entry.score));
+
+        //Only the top scores of each mode are kept
+        let mut kept = 0;
+        self.entries.retain(|entry| {
+            if entry.mode != mode {
+                return true;
+            }
+            kept += 1;
+            kept <= MAX_HIGH_SCORES
+        });
+    }
+}
+
+fn read_entries(path: &Path) -> Result<Vec<HighScoreEntry>, Box<dyn Error>> {
+    let mut reader = csv::Reader::from_path(path)?;
+    let mut entries = Vec::new();
+    for entry in reader.deserialize() {
+        entries.push(entry?);
+    }
+    Ok(entries)
+}
+
+//Set when the run that just ended made it onto the table and still needs a name
+pub struct PendingHighScore(pub Option<HighScoreEntry>);
+
+struct NameEntry {
+    name: String,
+    confirmed: bool,
+}
+
+struct HighScoreFilter(GameMode);
+
+#[derive(Component)]
+struct HighScoreUi;
+
+#[derive(Component)]
+struct NameText;
+
+#[derive(Component)]
+struct HighScoreTitle;
+
+#[derive(Component)]
+struct HighScoreList;
+
+#[derive(Component, Clone, Copy)]
+enum HighScoreButton {
+    Back,
+}
+
+//A replayed run already had its chance at the table when it was played
+fn check_high_score(
+    score: Res<PlayerScore>,
+    game_mode: Res<GameMode>,
+    round_tracker: Res<RoundTracker>,
+    table: Res<HighScoreTable>,
+    replay: Res<ReplayPlayback>,
+    mut pending: ResMut<PendingHighScore>,
+) {
+    if !replay.is_playing() && table.qualifies(*game_mode, score.0) {
+        pending.0 = Some(HighScoreEntry {
+            name: String::new(),
+            score: score.0,
+            mode: *game_mode,
+            round: round_tracker.current_round.unwrap_or(0),
+        });
+    }
+}
+
+fn prompt_name_entry(pending: Res<PendingHighScore>, mut state: ResMut<State<GameState>>) {
+    if pending.0.is_some() {
+        let _ = state.push(GameState::NameEntry);
+    }
+}
+
+fn text_style(ui_assets: &UIAssets) -> TextStyle {
+    TextStyle {
+        font: ui_assets.font.clone(),
+        font_size: 40.0,
+        color: Color::rgb(0.9, 0.9, 0.9),
+    }
+}
+
+fn spawn_screen_root<'w, 's, 'a>(commands: &'a mut Commands<'w, 's>) -> EntityCommands<'w, 's, 'a> {
+    let mut root = commands.spawn_bundle(NodeBundle {
+        style: Style {
+            size: Size::new(Val::Percent(100.0), Val::Percent(100.0)),
+            position_type: PositionType::Absolute,
+            flex_direction: FlexDirection::ColumnReverse,
+            align_items: AlignItems::Center,
+            justify_content: JustifyContent::Center,
+            ..Default::default()
+        },
+        color: Color::rgba(0.0, 0.0, 0.0, 0.8).into(),
+        ..Default::default()
+    });
+    root.insert(HighScoreUi);
+    root
+}
+
+fn setup_name_entry(mut commands: Commands, ui_assets: Res<UIAssets>) {
+    commands.insert_resource(NameEntry {
+        name: String::new(),
+        confirmed: false,
+    });
+
+    let style = text_style(&ui_assets);
+    spawn_screen_root(&mut commands).with_children(|parent| {
+        parent.spawn_bundle(TextBundle::from_section("New High Score!", style.clone()));
+        parent
+            .spawn_bundle(TextBundle::from_section("", style.clone()))
+            .insert(NameText);
+        parent.spawn_bundle(TextBundle::from_section(
+            "Type or use the D-Pad, Enter/A to confirm",
+            TextStyle {
+                font_size: 20.0,
+                ..style
+            },
+        ));
+    });
+}
+
+fn cycle_character(current: char, step: isize) -> char {
+    let index = NAME_CHARACTERS
+        .iter()
+        .position(|c| *c as char == current)
+        .unwrap_or(0) as isize;
+    let len = NAME_CHARACTERS.len() as isize;
+    NAME_CHARACTERS[(index + step).rem_euclid(len) as usize] as char
+}
+
+fn name_entry_input(
+    mut char_events: EventReader<ReceivedCharacter>,
+    keys: Res<Input<KeyCode>>,
+    controller: Option<Res<Controller>>,
+    buttons: Res<Input<GamepadButton>>,
+    mut entry: ResMut<NameEntry>,
+) {
+    for event in char_events.iter() {
+        let c = event.char.to_ascii_uppercase();
+        if NAME_CHARACTERS.contains(&(c as u8)) && entry.name.len() < MAX_NAME_LENGTH {
+            entry.name.push(c);
+        }
+    }
+    if keys.just_pressed(KeyCode::Back) {
+        entry.name.pop();
+    }
+    if keys.just_pressed(KeyCode::Return) {
+        entry.confirmed = true;
+    }
+
+    //Arcade style entry, up and down pick the last letter, right adds a new one
+    if let Some(controller) = controller {
+        let pressed =
+            |button_type| buttons.just_pressed(GamepadButton::new(controller.0, button_type));
+        if pressed(GamepadButtonType::DPadRight) && entry.name.len() < MAX_NAME_LENGTH {
+            entry.name.push('A');
+        }
+        if pressed(GamepadButtonType::DPadLeft) || pressed(GamepadButtonType::East) {
+            entry.name.pop();
+        }
+        for (button_type, step) in [
+            (GamepadButtonType::DPadUp, 1),
+            (GamepadButtonType::DPadDown, -1),
+        ] {
+            if pressed(button_type) {
+                match entry.name.pop() {
+                    Some(last) => entry.name.push(cycle_character(last, step)),
+                    None => entry.name.push('A'),
+                }
+            }
+        }
+        if pressed(GamepadButtonType::South) {
+            entry.confirmed = true;
+        }
+    }
+}
+
+fn update_name_entry_text(entry: Res<NameEntry>, mut query: Query<&mut Text, With<NameText>>) {
+    if entry.is_changed() {
+        for mut text in query.iter_mut() {
+            text.sections[0].value = format!("{}_", entry.name);
+        }
+    }
+}
+
+fn confirm_name_entry(
+    entry: Res<NameEntry>,
+    mut pending: ResMut<PendingHighScore>,
+    mut table: ResMut<HighScoreTable>,
+    mut state: ResMut<State<GameState>>,
+) {
+    if !entry.confirmed {
+        return;
+    }
+    if let Some(mut high_score) = pending.0.take() {
+        high_score.name = match entry.name.trim() {
+            "" => DEFAULT_NAME.to_string(),
+            name => name.to_string(),
+        };
+        table.insert(high_score);
+        if let Err(err) = table.save() {
+            eprintln!("Failed to save high scores: {}", err);
+        }
+    }
+    let _ = state.pop();
+}
+
+fn setup_high_score_screen(
+    mut commands: Commands,
+    ui_assets: Res<UIAssets>,
+    game_mode: Res<GameMode>,
+) {
+    commands.insert_resource(HighScoreFilter(*game_mode));
+
+    MenuBuilder::new()
+        .backdrop(Color::rgba(0.0, 0.0, 0.0, 0.8))
+        .tagged_text("", 40.0, HighScoreTitle)
+        .tagged_text("", 28.0, HighScoreList)
+        .text("Left/Right to change mode", 20.0)
+        .button("Back", HighScoreButton::Back)
+        .spawn(&mut commands, &ui_assets)
+        .insert(HighScoreUi);
+}
+
+fn high_score_screen_input(
+    keys: Res<Input<KeyCode>>,
+    controller: Option<Res<Controller>>,
+    buttons: Res<Input<GamepadButton>>,
+    mut filter: ResMut<HighScoreFilter>,
+    mut state: ResMut<State<GameState>>,
+) {
+    let mut change_mode = keys.any_just_pressed([KeyCode::Left, KeyCode::Right]);
+    let mut back = keys.just_pressed(KeyCode::Escape);
+
+    if let Some(controller) = controller {
+        let pressed =
+            |button_type| buttons.just_pressed(GamepadButton::new(controller.0, button_type));
+        change_mode |=
+            pressed(GamepadButtonType::DPadLeft) || pressed(GamepadButtonType::DPadRight);
+        back |= pressed(GamepadButtonType::East);
+    }
+
+    if change_mode {
+        filter.0 = filter.0.next();
+    }
+    if back {
+        let _ = state.pop();
+    }
+}
+
+fn handle_high_score_buttons(
+    mut selected: EventReader<MenuSelected<HighScoreButton>>,
+    mut state: ResMut<State<GameState>>,
+) {
+    for MenuSelected(action) in selected.iter() {
+        match action {
+            HighScoreButton::Back => {
+                let _ = state.pop();
+            }
+        }
+    }
+}
+
+fn update_high_score_list(
+    filter: Res<HighScoreFilter>,
+    table: Res<HighScoreTable>,
+    mut title_query: Query<&mut Text, (With<HighScoreTitle>, Without<HighScoreList>)>,
+    mut list_query: Query<&mut Text, (With<HighScoreList>, Without<HighScoreTitle>)>,
+) {
+    if !filter.is_changed() && !table.is_changed() {
+        return;
+    }
+    for mut title in title_query.iter_mut() {
+        title.sections[0].value = format!("High Scores - {}", filter.0.name());
+    }
+
+    let mut lines: Vec<String> = table
+        .for_mode(filter.0)
+        .enumerate()
+        .map(|(rank, entry)| {
+            format!(
+                "{:>2}. {:<10} {:>8}  round {}",
+                rank + 1,
+                entry.name,
+                entry.score,
+                entry.round
+            )
+        })
+        .collect();
+    if lines.is_empty() {
+        lines.push("No scores yet".to_string());
+    }
+    for mut list in list_query.iter_mut() {
+        list.sections[0].value = lines.join("\n");
+    }
+}
+
+fn despawn_high_score_ui(mut commands: Commands, query: Query<Entity, With<HighScoreUi>>) {
+    for entity in query.iter() {
+        commands.entity(entity).despawn_recursive();
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    //A fresh directory per test, so tests running side by side don't trip over each other's files
+    fn scratch_dir(name: &str) -> PathBuf {
+        let dir = std::env::temp_dir().join("mbrh_high_scores").join(name);
+        let _ = fs::remove_dir_all(&dir);
+        fs::create_dir_all(&dir).unwrap();
+        dir
+    }
+
+    fn entry(name: &str, score: usize, mode: GameMode) -> HighScoreEntry {
+        HighScoreEntry {
+            name: name.to_string(),
+            score,
+            mode,
+            round: 1,
+        }
+    }
+
+    #[test]
+    fn missing_file_is_an_empty_table() {
+        let path = scratch_dir("missing").join("high_scores.csv");
+        let table = HighScoreTable::load(path);
+        assert_eq!(table.for_mode(GameMode::Classic).count(), 0);
+    }
+
+    #[test]
+    fn saved_table_loads_back() {
+        let path = scratch_dir("round_trip").join("high_scores.csv");
+        let mut table = HighScoreTable::load(path.clone());
+        table.insert(entry("QWARK", 300, GameMode::Classic));
+        table.insert(entry("RATCHET", 500, GameMode::Classic));
+        table.insert(entry("CLANK", 200, GameMode::Endless));
+        table.save().unwrap();
+        assert!(!path.with_extension("csv.tmp").exists());
+
+        let loaded = HighScoreTable::load(path);
+        let classic: Vec<(&str, usize)> = loaded
+            .for_mode(GameMode::Classic)
+            .map(|entry| (entry.name.as_str(), entry.score))
+            .collect();
+        assert_eq!(classic, [("RATCHET", 500), ("QWARK", 300)]);
+        assert_eq!(loaded.best(GameMode::Endless), Some(200));
+    }
+
+    #[test]
+    fn corrupted_file_is_moved_aside() {
+        let path = scratch_dir("corrupted").join("high_scores.csv");
+        fs::write(&path, "name,score\nnot,a,table\n").unwrap();
+
+        let table = HighScoreTable::load(path.clone());
+        assert_eq!(table.for_mode(GameMode::Classic).count(), 0);
+        assert!(!path.exists());
+        let backup = fs::read_to_string(path.with_extension("csv.bak")).unwrap();
+        assert_eq!(backup, "name,score\nnot,a,table\n");
+    }
+
+    #[test]
+    fn only_the_top_scores_of_each_mode_are_kept() {
+        let path = scratch_dir("top_scores").join("high_scores.csv");
+        let mut table = HighScoreTable::load(path);
+        table.insert(entry("CLANK", 10, GameMode::Endless));
+        for score in 1..=MAX_HIGH_SCORES + 2 {
+            table.insert(entry("QWARK", score * 100, GameMode::Classic));
+        }
+        assert_eq!(table.for_mode(GameMode::Classic).count(), MAX_HIGH_SCORES);
+        assert!(!table.qualifies(GameMode::Classic, 200));
+        assert_eq!(table.for_mode(GameMode::Endless).count(), 1);
+    }
+}
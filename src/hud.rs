@@ -0,0 +1,208 @@
+use bevy::prelude::*;
+
+use crate::components::{
+    GameUi, Health, HealthPip, HeatBar, HeatWarningText, HudText, LifeIcon, Player, PlayerLivesUI,
+};
+use crate::constants::*;
+use crate::resources::{BlasterHeat, GameFont, GameTextures, PlayerLives, WindowSize};
+use crate::states::GameState;
+
+//The heat gauge, health and lives. Sizes are percentages of the window so it all scales together,
+//text is rescaled separately since fonts only come in pixels
+pub struct HudPlugin;
+
+impl Plugin for HudPlugin {
+    fn build(&self, app: &mut App) {
+        app.add_system_set(SystemSet::on_enter(GameState::MainGame).with_system(spawn_hud_system))
+            .add_system_set(
+                SystemSet::on_update(GameState::MainGame)
+                    .with_system(update_heat_bar_system)
+                    .with_system(update_heat_warning_system)
+                    .with_system(update_health_pips_system)
+                    .with_system(update_life_icons_system),
+            )
+            .add_system(scale_hud_text_system);
+    }
+}
+
+const GAUGE_BACKGROUND: Color = Color::rgb(0.15, 0.15, 0.15);
+const COOL_COLOR: Color = Color::rgb(0.0, 0.8, 0.0);
+const HOT_COLOR: Color = Color::rgb(1.0, 0.8, 0.0);
+const POWER_SHOT_COLOR: Color = Color::rgb(0.0, 1.0, 1.0);
+const OVERHEAT_COLOR: Color = Color::rgb(1.0, 0.1, 0.0);
+const EMPTY_PIP_COLOR: Color = Color::rgb(0.25, 0.05, 0.05);
+const HEALTH_PIP_COLOR: Color = Color::rgb(0.9, 0.1, 0.1);
+
+fn absolute(left: f32, top: f32, width: f32, height: f32) -> Style {
+    Style {
+        position_type: PositionType::Absolute,
+        position: UiRect {
+            left: Val::Percent(left),
+            top: Val::Percent(top),
+            ..default()
+        },
+        size: Size::new(Val::Percent(width), Val::Percent(height)),
+        ..default()
+    }
+}
+
+fn spawn_hud_system(mut cmds: Commands, font: Res<GameFont>, game_textures: Res<GameTextures>) {
+    //Heat gauge along the bottom middle, with a notch where power shots start
+    cmds.spawn_bundle(NodeBundle {
+        style: absolute(35.0, 93.0, 30.0, 3.0),
+        color: GAUGE_BACKGROUND.into(),
+        ..default()
+    })
+    .insert(GameUi)
+    .with_children(|parent| {
+        parent
+            .spawn_bundle(NodeBundle {
+                style: Style {
+                    size: Size::new(Val::Percent(0.0), Val::Percent(100.0)),
+                    ..default()
+                },
+                color: COOL_COLOR.into(),
+                ..default()
+            })
+            .insert(HeatBar);
+        parent.spawn_bundle(NodeBundle {
+            style: absolute(
+                BLASTER_POWER_SHOT_THRESHOLD / MAX_BLASTER_HEAT * 100.0,
+                0.0,
+                0.5,
+                100.0,
+            ),
+            color: Color::rgb(0.9, 0.9, 0.9).into(),
+            ..default()
+        });
+    });
+
+    cmds.spawn_bundle(
+        TextBundle::from_section(
+            "",
+            TextStyle {
+                font: font.0.clone(),
+                font_size: 28.0,
+                color: OVERHEAT_COLOR,
+            },
+        )
+        .with_style(Style {
+            position_type: PositionType::Absolute,
+            position: UiRect {
+                left: Val::Percent(35.0),
+                top: Val::Percent(88.0),
+                ..default()
+            },
+            ..default()
+        }),
+    )
+    .insert(HeatWarningText)
+    .insert(HudText { base_size: 28.0 })
+    .insert(GameUi);
+
+    for pip in 0..PLAYER_HEALTH {
+        cmds.spawn_bundle(NodeBundle {
+            style: absolute(1.0 + pip as f32 * 3.0, 88.0, 2.5, 3.0),
+            color: HEALTH_PIP_COLOR.into(),
+            ..default()
+        })
+        .insert(HealthPip(pip))
+        .insert(GameUi);
+    }
+
+    for life in 0..PLAYER_LIVES {
+        cmds.spawn_bundle(ImageBundle {
+            style: absolute(1.0 + life as f32 * 5.0, 80.0, 4.0, 6.0),
+            image: game_textures.player.clone().into(),
+            ..default()
+        })
+        .insert(LifeIcon(life))
+        .insert(PlayerLivesUI)
+        .insert(GameUi);
+    }
+}
+
+fn heat_color(blaster_heat: &BlasterHeat) -> Color {
+    if !blaster_heat.overheat_cooldown_timer.ready() {
+        return OVERHEAT_COLOR;
+    }
+    if blaster_heat.value >= BLASTER_POWER_SHOT_THRESHOLD {
+        return POWER_SHOT_COLOR;
+    }
+    //Fades from green to yellow on the way up to the power shot threshold
+    let t = (blaster_heat.value / BLASTER_POWER_SHOT_THRESHOLD).clamp(0.0, 1.0);
+    Color::rgb(
+        COOL_COLOR.r() + (HOT_COLOR.r() - COOL_COLOR.r()) * t,
+        COOL_COLOR.g() + (HOT_COLOR.g() - COOL_COLOR.g()) * t,
+        COOL_COLOR.b() + (HOT_COLOR.b() - COOL_COLOR.b()) * t,
+    )
+}
+
+fn update_heat_bar_system(
+    blaster_heat: Res<BlasterHeat>,
+    mut query: Query<(&mut Style, &mut UiColor), With<HeatBar>>,
+) {
+    for (mut style, mut color) in query.iter_mut() {
+        let fill = (blaster_heat.value / MAX_BLASTER_HEAT).clamp(0.0, 1.0);
+        style.size.width = Val::Percent(fill * 100.0);
+        *color = heat_color(&blaster_heat).into();
+    }
+}
+
+fn update_heat_warning_system(
+    blaster_heat: Res<BlasterHeat>,
+    mut query: Query<&mut Text, With<HeatWarningText>>,
+) {
+    for mut text in query.iter_mut() {
+        let section = &mut text.sections[0];
+        match blaster_heat.overheat_cooldown_timer.remaining() {
+            Some(remaining) => {
+                section.value = format!("OVERHEAT {:.1}s", remaining.as_secs_f32());
+                section.style.color = OVERHEAT_COLOR;
+            }
+            None if blaster_heat.value >= BLASTER_POWER_SHOT_THRESHOLD => {
+                section.value = "POWER SHOT".to_string();
+                section.style.color = POWER_SHOT_COLOR;
+            }
+            None => section.value.clear(),
+        }
+    }
+}
+
+fn update_health_pips_system(
+    player_query: Query<&Health, With<Player>>,
+    mut pip_query: Query<(&HealthPip, &mut UiColor)>,
+) {
+    if let Ok(health) = player_query.get_single() {
+        for (pip, mut color) in pip_query.iter_mut() {
+            *color = if pip.0 < health.health {
+                HEALTH_PIP_COLOR.into()
+            } else {
+                EMPTY_PIP_COLOR.into()
+            };
+        }
+    }
+}
+
+fn update_life_icons_system(
+    lives: Res<PlayerLives>,
+    mut icon_query: Query<(&LifeIcon, &mut Visibility)>,
+) {
+    for (icon, mut visibility) in icon_query.iter_mut() {
+        visibility.is_visible = icon.0 < lives.0;
+    }
+}
+
+fn scale_hud_text_system(
+    win_size: Res<WindowSize>,
+    mut query: Query<(&mut Text, &HudText, ChangeTrackers<HudText>)>,
+) {
+    let scale = win_size.h / HUD_REFERENCE_HEIGHT;
+    for (mut text, hud_text, hud_text_tracker) in query.iter_mut() {
+        if win_size.is_changed() || hud_text_tracker.is_added() {
+            for section in text.sections.iter_mut() {
+                section.style.font_size = hud_text.base_size * scale;
+            }
+        }
+    }
+}
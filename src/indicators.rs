@@ -0,0 +1,145 @@
+use std::f32::consts::FRAC_PI_2;
+
+use bevy::prelude::*;
+use bevy::sprite::{MaterialMesh2dBundle, Mesh2dHandle};
+
+use crate::components::{Background, Civilian, Critical, Dead, Enemy};
+use crate::constants::{CRITICAL_INDICATOR_SCALE, INDICATOR_EDGE_MARGIN, INDICATOR_SIZE};
+use crate::resources::{SpawnQueue, WindowSize};
+use crate::states::GameState;
+
+//Arrows around the edge of the screen pointing at enemies (red) and civilians (green) that are out of
+//view. Each target gets its own arrow which is hidden while the target is on screen
+pub struct IndicatorPlugin;
+
+impl Plugin for IndicatorPlugin {
+    fn build(&self, app: &mut App) {
+        app.add_startup_system(load_indicator_assets)
+            .add_system_set(
+                SystemSet::on_update(GameState::MainGame)
+                    .with_system(spawn_indicators_system)
+                    .with_system(mark_critical_system)
+                    .with_system(update_indicators_system.after(mark_critical_system)),
+            );
+    }
+}
+
+struct IndicatorAssets {
+    arrow: Mesh2dHandle,
+    enemy: Handle<ColorMaterial>,
+    civilian: Handle<ColorMaterial>,
+}
+
+#[derive(Component)]
+struct OffscreenIndicator {
+    target: Entity,
+}
+
+fn load_indicator_assets(
+    mut commands: Commands,
+    mut meshes: ResMut<Assets<Mesh>>,
+    mut materials: ResMut<Assets<ColorMaterial>>,
+) {
+    commands.insert_resource(IndicatorAssets {
+        arrow: meshes
+            .add(Mesh::from(shape::RegularPolygon::new(INDICATOR_SIZE, 3)))
+            .into(),
+        enemy: materials.add(ColorMaterial::from(Color::rgb(1.0, 0.1, 0.1))),
+        civilian: materials.add(ColorMaterial::from(Color::rgb(0.1, 1.0, 0.1))),
+    });
+}
+
+fn spawn_indicators_system(
+    mut cmds: Commands,
+    assets: Res<IndicatorAssets>,
+    enemy_query: Query<Entity, Added<Enemy>>,
+    civilian_query: Query<Entity, Added<Civilian>>,
+) {
+    let targets = enemy_query
+        .iter()
+        .map(|entity| (entity, assets.enemy.clone()))
+        .chain(
+            civilian_query
+                .iter()
+                .map(|entity| (entity, assets.civilian.clone())),
+        );
+    for (target, material) in targets {
+        cmds.spawn_bundle(MaterialMesh2dBundle {
+            mesh: assets.arrow.clone(),
+            material,
+            visibility: Visibility { is_visible: false },
+            ..default()
+        })
+        .insert(OffscreenIndicator { target })
+        .insert(Background);
+    }
+}
+
+type LiveEnemies = (With<Enemy>, Without<Dead>);
+
+//The last enemy of a round, with nothing left to spawn, is the one standing between the player and
+//the next round
+fn mark_critical_system(
+    mut cmds: Commands,
+    spawn_queue: Res<SpawnQueue>,
+    enemy_query: Query<(Entity, Option<&Critical>), LiveEnemies>,
+) {
+    if !spawn_queue.is_empty() {
+        return;
+    }
+    let mut enemies = enemy_query.iter();
+    if let (Some((last, None)), None) = (enemies.next(), enemies.next()) {
+        cmds.entity(last).insert(Critical);
+    }
+}
+
+type LiveTargets = (Without<Dead>, Without<OffscreenIndicator>);
+
+fn update_indicators_system(
+    mut cmds: Commands,
+    time: Res<Time>,
+    win_size: Res<WindowSize>,
+    camera_query: Query<&Transform, With<Camera2d>>,
+    target_query: Query<(&Transform, Option<&Critical>), LiveTargets>,
+    mut indicator_query: Query<
+        (Entity, &OffscreenIndicator, &mut Transform, &mut Visibility),
+        Without<Camera2d>,
+    >,
+) {
+    let camera = match camera_query.get_single() {
+        Ok(camera) => camera.translation.truncate(),
+        Err(_) => return,
+    };
+    let half_view = Vec2::new(win_size.w / 2.0, win_size.h / 2.0);
+    let edge = half_view - Vec2::splat(INDICATOR_EDGE_MARGIN);
+
+    for (entity, indicator, mut transform, mut visibility) in indicator_query.iter_mut() {
+        //Dead targets lose their arrow straight away rather than when they're finally despawned
+        let (target_tf, critical) = match target_query.get(indicator.target) {
+            Ok(target) => target,
+            Err(_) => {
+                cmds.entity(entity).despawn_recursive();
+                continue;
+            }
+        };
+
+        let offset = target_tf.translation.truncate() - camera;
+        if offset.x.abs() <= half_view.x && offset.y.abs() <= half_view.y {
+            visibility.is_visible = false;
+            continue;
+        }
+
+        //Pull the arrow in along the line to the target until it sits just inside the screen edge
+        let scale = (edge.x / offset.x.abs()).min(edge.y / offset.y.abs());
+        let position = camera + offset * scale;
+        transform.translation = position.extend(20.0);
+        transform.rotation = Quat::from_rotation_z(offset.y.atan2(offset.x) - FRAC_PI_2);
+        transform.scale = if critical.is_some() {
+            let pulse = 1.0 + 0.2 * (time.seconds_since_startup() as f32 * 8.0).sin();
+            Vec3::splat(CRITICAL_INDICATOR_SCALE * pulse)
+        } else {
+            Vec3::ONE
+        };
+        visibility.is_visible = true;
+    }
+}
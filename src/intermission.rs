@@ -0,0 +1,147 @@
+use bevy::prelude::*;
+use bevy_rapier2d::prelude::*;
+
+use crate::constants::INTERMISSION_SECONDS;
+use crate::menu::{MenuActionPlugin, MenuBuilder, MenuSelected, UIAssets};
+use crate::resources::{PlayerScore, SpawnQueue};
+use crate::rounds::{start_round, RoundTracker};
+use crate::spawn_manager::NewRoundEvent;
+use crate::states::GameState;
+use crate::stats::RoundStats;
+
+pub struct IntermissionPlugin;
+
+impl Plugin for IntermissionPlugin {
+    fn build(&self, app: &mut App) {
+        app.add_plugin(MenuActionPlugin::<IntermissionButton>::default())
+            .add_system_set(
+                SystemSet::on_enter(GameState::Intermission)
+                    .with_system(pause_physics)
+                    .with_system(setup_intermission),
+            )
+            .add_system_set(
+                SystemSet::on_update(GameState::Intermission)
+                    .with_system(countdown_system)
+                    .with_system(handle_intermission_buttons)
+                    .with_system(
+                        start_next_round_system
+                            .after(countdown_system)
+                            .after(handle_intermission_buttons),
+                    ),
+            )
+            .add_system_set(
+                SystemSet::on_exit(GameState::Intermission)
+                    .with_system(despawn_intermission)
+                    .with_system(resume_physics),
+            );
+    }
+}
+
+#[derive(Component)]
+struct IntermissionUi;
+
+#[derive(Component)]
+struct CountdownText;
+
+#[derive(Component, Clone, Copy)]
+enum IntermissionButton {
+    Continue,
+}
+
+struct IntermissionCountdown {
+    timer: Timer,
+    done: bool,
+}
+
+//The player entity and everything else in the world stays put, physics just stops stepping
+pub fn pause_physics(mut rapier_config: ResMut<RapierConfiguration>) {
+    rapier_config.physics_pipeline_active = false;
+}
+
+pub fn resume_physics(mut rapier_config: ResMut<RapierConfiguration>) {
+    rapier_config.physics_pipeline_active = true;
+}
+
+fn setup_intermission(
+    mut commands: Commands,
+    ui_assets: Res<UIAssets>,
+    stats: Res<RoundStats>,
+    score: Res<PlayerScore>,
+) {
+    commands.insert_resource(IntermissionCountdown {
+        timer: Timer::from_seconds(INTERMISSION_SECONDS, false),
+        done: false,
+    });
+
+    let seconds = stats.seconds as u32;
+    let lines = [
+        format!("Round {} Clear!", stats.round),
+        format!("Kills: {}", stats.kills),
+        format!(
+            "Civilians Saved: {}  Lost: {}",
+            stats.civilians_saved, stats.civilians_lost
+        ),
+        format!("Accuracy: {:.0}%", stats.accuracy() * 100.),
+        format!("Time: {}:{:02}", seconds / 60, seconds % 60),
+        format!("Score: +{}", stats.score_gained(&score)),
+    ];
+
+    let mut menu = MenuBuilder::new().backdrop(Color::rgba(0.0, 0.0, 0.0, 0.6));
+    for line in lines {
+        menu = menu.text(line, 32.0);
+    }
+    menu.tagged_text("", 32.0, CountdownText)
+        .button("Continue", IntermissionButton::Continue)
+        .spawn(&mut commands, &ui_assets)
+        .insert(IntermissionUi);
+}
+
+fn countdown_system(
+    time: Res<Time>,
+    mut countdown: ResMut<IntermissionCountdown>,
+    mut query: Query<&mut Text, With<CountdownText>>,
+) {
+    countdown.timer.tick(time.delta());
+    if countdown.timer.finished() {
+        countdown.done = true;
+    }
+
+    let remaining = (countdown.timer.duration() - countdown.timer.elapsed()).as_secs_f32();
+    for mut text in query.iter_mut() {
+        text.sections[0].value = format!("Next round in {}", remaining.ceil() as u32);
+    }
+}
+
+fn handle_intermission_buttons(
+    mut selected: EventReader<MenuSelected<IntermissionButton>>,
+    mut countdown: ResMut<IntermissionCountdown>,
+) {
+    for MenuSelected(action) in selected.iter() {
+        match action {
+            IntermissionButton::Continue => countdown.done = true,
+        }
+    }
+}
+
+fn start_next_round_system(
+    countdown: Res<IntermissionCountdown>,
+    mut round_tracker: ResMut<RoundTracker>,
+    mut spawn_queue: ResMut<SpawnQueue>,
+    mut send_new_round: EventWriter<NewRoundEvent>,
+    mut state: ResMut<State<GameState>>,
+) {
+    if !countdown.done {
+        return;
+    }
+    //Nothing is left to do once the last round is cleared, the spawn manager goes to game over instead
+    if round_tracker.next_round() {
+        start_round(&mut spawn_queue, &round_tracker, &mut send_new_round);
+    }
+    let _ = state.pop();
+}
+
+fn despawn_intermission(mut commands: Commands, query: Query<Entity, With<IntermissionUi>>) {
+    for entity in query.iter() {
+        commands.entity(entity).despawn_recursive();
+    }
+}
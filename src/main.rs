@@ -1,30 +1,55 @@
 #![allow(unused)]
 
-use bevy::{prelude::*, render::texture::ImageSettings};
+use std::path::Path;
+
+use bevy::{app::PluginGroupBuilder, prelude::*, render::texture::ImageSettings};
 use bevy_rapier2d::prelude::*;
 
 const QWARK_SPRITE: &str = "qwark.png";
 const QWARK_SIZE: (f32, f32) = (500., 500.);
 const ENEMY_SPRITE: &str = "tux.png";
 const ENEMY_SIZE: (f32, f32) = (500., 500.);
+const SHADOW_SPRITE: &str = "darians-assets/TeamGunner/EXTRAS/Shadow.png";
 
+mod announcer;
+mod banner;
 mod blaster;
 mod camera;
 mod civilian;
+mod combat_text;
 mod components;
 mod constants;
+mod controls;
 mod debug;
+mod depth;
 mod enemy;
+mod fixed_step;
 mod game_over;
 mod gamepad;
 mod graphics;
+mod headless;
+mod high_scores;
+mod hud;
+mod indicators;
+mod intermission;
 mod main_menu;
+mod menu;
+mod music;
+mod particles;
+mod pause;
 mod player;
 mod projectile_collision;
+mod replay;
 mod resources;
 mod rounds;
+mod scoring;
+mod session;
+mod settings;
+mod sound;
 mod spawn_manager;
 mod states;
+mod stats;
+mod tint;
 mod ui;
 mod utils;
 
@@ -32,62 +57,108 @@ use constants::*;
 use game_over::GameOverMenuPlugin;
 use main_menu::MainMenuPlugin;
 use projectile_collision::CollisionPlugin;
+use replay::{QueuedReplay, Replay};
 use resources::{BlasterHeat, GameFont, GameTextures, PlayerLives, PlayerScore, WindowSize};
+use settings::Settings;
 use states::GameState;
 use utils::CooldownTimer;
 
 fn main() {
+    let args: Vec<String> = std::env::args().collect();
+    //Watched from the main menu as soon as the game is up, or played out headless
+    let replay = arg_value(&args, "--replay").map(|path| match Replay::load(Path::new(path)) {
+        Ok(replay) => replay,
+        Err(err) => {
+            eprintln!("Couldn't load {}: {}", path, err);
+            std::process::exit(1);
+        }
+    });
+    if let Some(script) = arg_value(&args, "--headless") {
+        headless::run(Path::new(script), replay);
+        return;
+    }
+
+    let settings = Settings::load(Settings::default_path());
+
     App::new()
         .insert_resource(ClearColor(Color::rgb(0.04, 0.04, 0.04)))
-        .insert_resource(WindowDescriptor {
-            title: "My Blaster Runs Hot".to_string(),
-            width: 1024.,
-            height: 768.,
-            ..Default::default()
-        })
+        .insert_resource(settings.window_descriptor())
+        .insert_resource(settings)
+        .insert_resource(QueuedReplay(replay))
         .insert_resource(ImageSettings::default_nearest()) //Prevents blurry images apparently
         .add_plugins(DefaultPlugins)
-        .add_plugin(RapierPhysicsPlugin::<NoUserData>::pixels_per_meter(100.0))
-        .add_plugin(RapierDebugRenderPlugin::default())
+        .add_plugin(fixed_step::FixedStepPlugin)
         .add_state(states::GameState::MainMenu)
         //start plugins
+        .add_plugin(menu::MenuPlugin)
         .add_plugin(MainMenuPlugin)
-        .add_plugin(civilian::CivilianPlugin)
-        //.add_plugin(gamepad::GamepadPlugin)
-        .add_plugin(player::PlayerPlugin)
-        .add_plugin(graphics::AnimationPlugin)
-        .add_plugin(blaster::BlasterPlugin)
-        .add_plugin(enemy::EnemyPlugin)
-        .add_plugin(projectile_collision::CollisionPlugin)
-        .add_plugin(rounds::RoundManagerPlugin)
-        .add_plugin(spawn_manager::SpawnManagerPlugin)
+        .add_plugin(gamepad::GamepadPlugin)
+        .add_plugin(controls::ControlsPlugin)
+        //Between the controls and the game, so a replay's input replaces the live input before it's used
+        .add_plugin(replay::ReplayPlugin)
+        .add_plugins(GameplayPlugins)
+        .add_plugin(RapierDebugRenderPlugin::default())
         .add_plugin(ui::UiPlugin)
+        .add_plugin(hud::HudPlugin)
+        .add_plugin(banner::BannerPlugin)
+        .add_plugin(indicators::IndicatorPlugin)
+        .add_plugin(combat_text::CombatTextPlugin)
+        .add_plugin(particles::ParticlePlugin)
+        .add_plugin(depth::DepthPlugin)
+        .add_plugin(tint::TintPlugin)
+        .add_plugin(sound::SoundPlugin)
+        .add_plugin(music::MusicPlugin)
+        .add_plugin(announcer::AnnouncerPlugin)
+        .add_plugin(pause::PausePlugin)
+        .add_plugin(session::SessionPlugin)
+        .add_plugin(settings::SettingsPlugin)
         .add_plugin(GameOverMenuPlugin)
+        .add_plugin(high_scores::HighScorePlugin)
         //.add_plugin(WorldInspectorPlugin::new())
         //startup system
         .add_startup_system(setup_system)
+        .add_startup_system(window_size_system)
         .run();
 }
 
-fn setup_system(
-    mut cmds: Commands,
-    asset_server: Res<AssetServer>,
-    windows: Res<Windows>,
-    mut state: ResMut<State<GameState>>,
-) {
+//The game itself without anything that needs a window, a person or speakers. The windowed game adds
+//its screens, HUD and effects on top, the headless simulation runs these on their own. Anything that
+//decides how a run plays out ticks on the fixed step, which needs FixedStepPlugin added first
+pub struct GameplayPlugins;
+
+impl PluginGroup for GameplayPlugins {
+    fn build(&mut self, group: &mut PluginGroupBuilder) {
+        group
+            .add(civilian::CivilianPlugin)
+            .add(player::PlayerPlugin)
+            .add(graphics::AnimationPlugin)
+            .add(blaster::BlasterPlugin)
+            .add(enemy::EnemyPlugin)
+            .add(projectile_collision::CollisionPlugin)
+            .add(rounds::RoundManagerPlugin)
+            .add(spawn_manager::SpawnManagerPlugin)
+            .add(scoring::ScoringPlugin)
+            .add(stats::StatsPlugin)
+            .add(intermission::IntermissionPlugin);
+    }
+}
+
+//The value after a flag, so `--headless sim.ron` gives sim.ron for "--headless"
+fn arg_value<'a>(args: &'a [String], flag: &str) -> Option<&'a str> {
+    args.iter()
+        .position(|arg| arg == flag)
+        .and_then(|index| args.get(index + 1))
+        .map(|value| value.as_str())
+}
+
+fn setup_system(mut cmds: Commands, asset_server: Res<AssetServer>) {
     // Add the camera
     //cmds.spawn_bundle(Camera2dBundle::default());
 
-    // Add WinSize resource
-    let window = windows.get_primary().unwrap();
-    cmds.insert_resource(WindowSize {
-        w: window.width(),
-        h: window.height(),
-    });
-
     let game_textures = GameTextures {
         player: asset_server.load(QWARK_SPRITE),
         enemy: asset_server.load(ENEMY_SPRITE),
+        shadow: asset_server.load(SHADOW_SPRITE),
     };
     cmds.insert_resource(game_textures);
 
@@ -101,3 +172,12 @@ fn setup_system(
     let game_font = GameFont(asset_server.load("FiraSans-Bold.ttf"));
     cmds.insert_resource(game_font);
 }
+
+// Add WinSize resource
+fn window_size_system(mut cmds: Commands, windows: Res<Windows>) {
+    let window = windows.get_primary().unwrap();
+    cmds.insert_resource(WindowSize {
+        w: window.width(),
+        h: window.height(),
+    });
+}
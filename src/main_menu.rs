@@ -1,123 +1,86 @@
 // We need our game states so we can check what state we are in and states to
 // transition to
+use crate::camera::spawn_ui_camera_system;
+use crate::menu::{MenuActionPlugin, MenuBuilder, MenuSelected, UIAssets};
+use crate::replay::{QueuedReplay, Replay};
+use crate::resources::{GameMode, GameRng};
+use crate::rounds::{new_run_seed, seed_run, RoundTracker};
 use crate::states::GameState;
 
-// The Exit button is going to need to be able to close the game so we have to
-// use 'AppExit'
-use bevy::app::AppExit;
-
-use bevy::sprite::Rect;
-use bevy::{prelude::*, ui::FocusPolicy, ui::UiRect};
+use bevy::prelude::*;
 
+#[derive(Component)]
 pub struct MainMenu;
 
 pub struct MainMenuPlugin;
 
-#[derive(Component)]
-pub struct ButtonActive(pub bool);
-
-pub struct UIAssets {
-    pub font: Handle<Font>,
-    pub button: Handle<Image>,
-    pub button_pressed: Handle<Image>,
+#[derive(Component, Clone, Copy)]
+pub enum MainMenuButton {
+    Play(GameMode),
+    WatchLastRun,
+    HighScores,
+    Settings,
 }
 
 impl Plugin for MainMenuPlugin {
     fn build(&self, app: &mut App) {
-        app.add_startup_system(setup_menu)
+        app.add_plugin(MenuActionPlugin::<MainMenuButton>::default())
+            //The one camera lives for the whole app, sessions only clean up what they spawn
+            .add_startup_system(spawn_ui_camera_system)
+            .add_system_set(SystemSet::on_enter(GameState::MainMenu).with_system(setup_menu))
+            .add_system_set(SystemSet::on_resume(GameState::MainMenu).with_system(setup_menu))
             .add_system_set(SystemSet::on_pause(GameState::MainMenu).with_system(despawn_menu))
-            .add_system(handle_start_button);
+            //Quitting out of a game replaces the whole stack, which resumes the menu on the way down
+            .add_system_set(SystemSet::on_exit(GameState::MainMenu).with_system(despawn_menu))
+            .add_system_set(
+                SystemSet::on_update(GameState::MainMenu).with_system(handle_start_button),
+            );
     }
 }
 
-fn despawn_menu(mut commands: Commands, button_query: Query<Entity, With<Button>>) {
-    for entity in button_query.iter() {
+fn despawn_menu(mut commands: Commands, menu_query: Query<Entity, With<MainMenu>>) {
+    for entity in menu_query.iter() {
         commands.entity(entity).despawn_recursive();
     }
 }
 
 fn handle_start_button(
-    mut commands: Commands,
-    mut interaction_query: Query<
-        (&Children, &mut ButtonActive, &Interaction),
-        Changed<Interaction>,
-    >,
-    mut image_query: Query<&mut UiImage>,
+    mut selected: EventReader<MenuSelected<MainMenuButton>>,
     mut state: ResMut<State<GameState>>,
-    ui_assets: Res<UIAssets>,
+    mut game_mode: ResMut<GameMode>,
+    mut round_tracker: ResMut<RoundTracker>,
+    mut game_rng: ResMut<GameRng>,
+    mut queued_replay: ResMut<QueuedReplay>,
 ) {
-    for (children, mut active, interaction) in interaction_query.iter_mut() {
-        let child = children.iter().next().unwrap();
-        let mut image = image_query.get_mut(*child).unwrap();
-
-        match interaction {
-            Interaction::Clicked => {
-                if active.0 {
-                    image.0 = ui_assets.button_pressed.clone();
-                    state.push(GameState::MainGame).unwrap();
-                }
+    for MenuSelected(action) in selected.iter() {
+        match action {
+            MainMenuButton::Play(mode) => {
+                *game_mode = *mode;
+                seed_run(new_run_seed(), *mode, &mut round_tracker, &mut game_rng);
+                let _ = state.push(GameState::MainGame);
             }
-            Interaction::Hovered | Interaction::None => {
-                image.0 = ui_assets.button.clone();
+            //The replay starts itself from the queue
+            MainMenuButton::WatchLastRun => match Replay::load(&Replay::last_run_path()) {
+                Ok(replay) => queued_replay.0 = Some(replay),
+                Err(err) => eprintln!("Couldn't load the last run: {}", err),
+            },
+            MainMenuButton::HighScores => {
+                let _ = state.push(GameState::HighScores);
+            }
+            MainMenuButton::Settings => {
+                let _ = state.push(GameState::ControlMenu);
             }
         }
     }
 }
 
-fn setup_menu(mut commands: Commands, assets: Res<AssetServer>) {
-    let ui_assets = UIAssets {
-        font: assets.load("FiraSans-Bold.ttf"),
-        button: assets.load("button.png"),
-        button_pressed: assets.load("button_pressed.png"),
-    };
-
-    commands.spawn_bundle(Camera2dBundle::default());
-    commands
-        .spawn_bundle(ButtonBundle {
-            style: Style {
-                align_self: AlignSelf::Center,
-                align_items: AlignItems::Center,
-                justify_content: JustifyContent::Center,
-                size: Size::new(Val::Percent(20.0), Val::Percent(10.0)),
-                position: UiRect::new(
-                    Val::Percent(40.0),
-                    Val::Percent(0.0),
-                    Val::Percent(0.0),
-                    Val::Percent(0.0),
-                ),
-                ..Default::default()
-            },
-            color: Color::NONE.into(),
-            ..Default::default()
-        })
-        .insert(ButtonActive(true))
-        .with_children(|parent| {
-            parent
-                .spawn_bundle(ImageBundle {
-                    style: Style {
-                        size: Size::new(Val::Percent(100.0), Val::Percent(100.0)),
-                        justify_content: JustifyContent::Center,
-                        align_items: AlignItems::Center,
-                        ..Default::default()
-                    },
-                    image: ui_assets.button.clone().into(),
-                    ..Default::default()
-                })
-                .insert(FocusPolicy::Pass)
-                .with_children(|parent| {
-                    parent.spawn_bundle(TextBundle {
-                        text: Text::from_section(
-                            "Start Game",
-                            TextStyle {
-                                font: ui_assets.font.clone(),
-                                font_size: 40.0,
-                                color: Color::rgb(0.9, 0.9, 0.9),
-                            },
-                        ),
-                        focus_policy: FocusPolicy::Pass,
-                        ..Default::default()
-                    });
-                });
-        });
-    commands.insert_resource(ui_assets);
+fn setup_menu(mut commands: Commands, ui_assets: Res<UIAssets>) {
+    MenuBuilder::new()
+        .button("Start Game", MainMenuButton::Play(GameMode::Classic))
+        .button("Endless", MainMenuButton::Play(GameMode::Endless))
+        .button("Watch Last Run", MainMenuButton::WatchLastRun)
+        .button("High Scores", MainMenuButton::HighScores)
+        .button("Settings", MainMenuButton::Settings)
+        .spawn(&mut commands, &ui_assets)
+        .insert(MainMenu);
 }
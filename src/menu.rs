@@ -0,0 +1,421 @@
+use std::marker::PhantomData;
+
+use bevy::ecs::system::EntityCommands;
+use bevy::{prelude::*, ui::FocusPolicy};
+
+use crate::resources::Controller;
+use crate::sound::SoundEvent;
+
+//Shared pieces for every menu screen. A screen lists its buttons with a MenuBuilder, each button carries
+//an action of the screen's own type, and the screen reads MenuSelected events for that type to react
+pub struct MenuPlugin;
+
+#[derive(SystemLabel, Debug, Clone, PartialEq, Eq, Hash)]
+struct MenuNavigationLabel;
+
+impl Plugin for MenuPlugin {
+    fn build(&self, app: &mut App) {
+        app.add_startup_system(load_ui_assets)
+            .add_system(menu_navigation_system.label(MenuNavigationLabel))
+            .add_system(menu_button_visuals.after(MenuNavigationLabel));
+    }
+}
+
+//Needs adding once for every action type a screen uses
+pub struct MenuActionPlugin<A>(PhantomData<A>);
+
+impl<A> Default for MenuActionPlugin<A> {
+    fn default() -> Self {
+        Self(PhantomData)
+    }
+}
+
+impl<A: Component + Copy> Plugin for MenuActionPlugin<A> {
+    fn build(&self, app: &mut App) {
+        app.add_event::<MenuSelected<A>>()
+            .add_system(menu_selection_system::<A>.after(MenuNavigationLabel));
+    }
+}
+
+pub struct MenuSelected<A>(pub A);
+
+pub struct UIAssets {
+    pub font: Handle<Font>,
+    pub button: Handle<Image>,
+    pub button_pressed: Handle<Image>,
+}
+
+fn load_ui_assets(mut commands: Commands, assets: Res<AssetServer>) {
+    commands.insert_resource(UIAssets {
+        font: assets.load("FiraSans-Bold.ttf"),
+        button: assets.load("button.png"),
+        button_pressed: assets.load("button_pressed.png"),
+    });
+}
+
+//Root node of a menu, remembers which button keyboard and gamepad input is on
+#[derive(Component)]
+pub struct Menu {
+    focused: usize,
+}
+
+#[derive(Component)]
+pub struct MenuButton {
+    index: usize,
+    active: bool,
+}
+
+//On the text inside a button, for screens that change their labels while open
+#[derive(Component)]
+pub struct MenuLabel<A>(pub A);
+
+const FOCUSED_TINT: Color = Color::rgb(1.0, 0.85, 0.5);
+const INACTIVE_TEXT: Color = Color::rgb(0.4, 0.4, 0.4);
+const TEXT_COLOR: Color = Color::rgb(0.9, 0.9, 0.9);
+
+struct MenuItem<A> {
+    label: String,
+    action: A,
+    active: bool,
+}
+
+//Inserts a screen's marker on a text line
+type TextTag = Box<dyn FnOnce(&mut EntityCommands)>;
+
+struct MenuText {
+    text: String,
+    font_size: f32,
+    tag: Option<TextTag>,
+}
+
+pub struct MenuBuilder<A> {
+    title: Option<String>,
+    texts: Vec<MenuText>,
+    items: Vec<MenuItem<A>>,
+    backdrop: Color,
+    button_size: Size<Val>,
+    font_size: f32,
+}
+
+impl<A: Component + Copy> MenuBuilder<A> {
+    pub fn new() -> Self {
+        Self {
+            title: None,
+            texts: Vec::new(),
+            items: Vec::new(),
+            backdrop: Color::NONE,
+            button_size: Size::new(Val::Percent(20.0), Val::Percent(10.0)),
+            font_size: 40.0,
+        }
+    }
+
+    pub fn title(mut self, title: impl Into<String>) -> Self {
+        self.title = Some(title.into());
+        self
+    }
+
+    //A line of text between the title and the buttons
+    pub fn text(mut self, text: impl Into<String>, font_size: f32) -> Self {
+        self.texts.push(MenuText {
+            text: text.into(),
+            font_size,
+            tag: None,
+        });
+        self
+    }
+
+    //The same, with a marker for screens that change the text while open
+    pub fn tagged_text(
+        mut self,
+        text: impl Into<String>,
+        font_size: f32,
+        tag: impl Component,
+    ) -> Self {
+        self.texts.push(MenuText {
+            text: text.into(),
+            font_size,
+            tag: Some(Box::new(move |entity: &mut EntityCommands| {
+                entity.insert(tag);
+            })),
+        });
+        self
+    }
+
+    pub fn button(mut self, label: impl Into<String>, action: A) -> Self {
+        self.items.push(MenuItem {
+            label: label.into(),
+            action,
+            active: true,
+        });
+        self
+    }
+
+    //Shown greyed out and skipped by focus navigation
+    pub fn inactive_button(mut self, label: impl Into<String>, action: A) -> Self {
+        self.items.push(MenuItem {
+            label: label.into(),
+            action,
+            active: false,
+        });
+        self
+    }
+
+    pub fn backdrop(mut self, color: Color) -> Self {
+        self.backdrop = color;
+        self
+    }
+
+    pub fn button_size(mut self, size: Size<Val>) -> Self {
+        self.button_size = size;
+        self
+    }
+
+    pub fn font_size(mut self, font_size: f32) -> Self {
+        self.font_size = font_size;
+        self
+    }
+
+    //Returns the root so the screen can tag it with its own marker for despawning
+    pub fn spawn<'w, 's, 'a>(
+        self,
+        commands: &'a mut Commands<'w, 's>,
+        ui_assets: &UIAssets,
+    ) -> EntityCommands<'w, 's, 'a> {
+        let focused = self.items.iter().position(|item| item.active).unwrap_or(0);
+        let mut root = commands.spawn_bundle(NodeBundle {
+            style: Style {
+                size: Size::new(Val::Percent(100.0), Val::Percent(100.0)),
+                position_type: PositionType::Absolute,
+                flex_direction: FlexDirection::ColumnReverse,
+                align_items: AlignItems::Center,
+                justify_content: JustifyContent::Center,
+                ..Default::default()
+            },
+            color: self.backdrop.into(),
+            ..Default::default()
+        });
+        root.insert(Menu { focused }).with_children(|parent| {
+            if let Some(title) = &self.title {
+                parent.spawn_bundle(TextBundle::from_section(
+                    title.clone(),
+                    TextStyle {
+                        font: ui_assets.font.clone(),
+                        font_size: 60.0,
+                        color: TEXT_COLOR,
+                    },
+                ));
+            }
+            for text in self.texts {
+                let mut entity = parent.spawn_bundle(TextBundle::from_section(
+                    text.text,
+                    TextStyle {
+                        font: ui_assets.font.clone(),
+                        font_size: text.font_size,
+                        color: TEXT_COLOR,
+                    },
+                ));
+                if let Some(tag) = text.tag {
+                    tag(&mut entity);
+                }
+            }
+            for (index, item) in self.items.into_iter().enumerate() {
+                spawn_menu_button(
+                    parent,
+                    ui_assets,
+                    item,
+                    index,
+                    self.button_size,
+                    self.font_size,
+                );
+            }
+        });
+        root
+    }
+}
+
+fn spawn_menu_button<A: Component + Copy>(
+    parent: &mut ChildBuilder,
+    ui_assets: &UIAssets,
+    item: MenuItem<A>,
+    index: usize,
+    size: Size<Val>,
+    font_size: f32,
+) {
+    parent
+        .spawn_bundle(ButtonBundle {
+            style: Style {
+                align_items: AlignItems::Center,
+                justify_content: JustifyContent::Center,
+                size,
+                margin: UiRect::all(Val::Px(6.0)),
+                ..Default::default()
+            },
+            color: Color::NONE.into(),
+            ..Default::default()
+        })
+        .insert(MenuButton {
+            index,
+            active: item.active,
+        })
+        .insert(item.action)
+        .with_children(|parent| {
+            parent
+                .spawn_bundle(ImageBundle {
+                    style: Style {
+                        size: Size::new(Val::Percent(100.0), Val::Percent(100.0)),
+                        justify_content: JustifyContent::Center,
+                        align_items: AlignItems::Center,
+                        ..Default::default()
+                    },
+                    image: ui_assets.button.clone().into(),
+                    ..Default::default()
+                })
+                .insert(FocusPolicy::Pass)
+                .with_children(|parent| {
+                    parent
+                        .spawn_bundle(TextBundle {
+                            text: Text::from_section(
+                                item.label,
+                                TextStyle {
+                                    font: ui_assets.font.clone(),
+                                    font_size,
+                                    color: if item.active {
+                                        TEXT_COLOR
+                                    } else {
+                                        INACTIVE_TEXT
+                                    },
+                                },
+                            ),
+                            focus_policy: FocusPolicy::Pass,
+                            ..Default::default()
+                        })
+                        .insert(MenuLabel(item.action));
+                });
+        });
+}
+
+fn gamepad_just_pressed(
+    controller: &Option<Res<Controller>>,
+    buttons: &Input<GamepadButton>,
+    button_type: GamepadButtonType,
+) -> bool {
+    controller.as_ref().is_some_and(|controller| {
+        buttons.just_pressed(GamepadButton::new(controller.0, button_type))
+    })
+}
+
+//Arrow keys and the dpad move the focus, hovering with the mouse moves it too
+fn menu_navigation_system(
+    keys: Res<Input<KeyCode>>,
+    controller: Option<Res<Controller>>,
+    gamepad_buttons: Res<Input<GamepadButton>>,
+    mut menu_query: Query<(&mut Menu, &Children)>,
+    button_query: Query<(&MenuButton, &Interaction)>,
+) {
+    let up = keys.just_pressed(KeyCode::Up)
+        || gamepad_just_pressed(&controller, &gamepad_buttons, GamepadButtonType::DPadUp);
+    let down = keys.just_pressed(KeyCode::Down)
+        || gamepad_just_pressed(&controller, &gamepad_buttons, GamepadButtonType::DPadDown);
+
+    for (mut menu, children) in menu_query.iter_mut() {
+        let buttons: Vec<(&MenuButton, &Interaction)> = children
+            .iter()
+            .filter_map(|child| button_query.get(*child).ok())
+            .collect();
+        let active: Vec<usize> = buttons
+            .iter()
+            .filter(|(button, _)| button.active)
+            .map(|(button, _)| button.index)
+            .collect();
+        if active.is_empty() {
+            continue;
+        }
+
+        if let Some((button, _)) = buttons
+            .iter()
+            .find(|(button, interaction)| button.active && **interaction != Interaction::None)
+        {
+            if menu.focused != button.index {
+                menu.focused = button.index;
+            }
+            continue;
+        }
+
+        let current = active
+            .iter()
+            .position(|index| *index == menu.focused)
+            .unwrap_or(0);
+        if up {
+            menu.focused = active[(current + active.len() - 1) % active.len()];
+        } else if down {
+            menu.focused = active[(current + 1) % active.len()];
+        }
+    }
+}
+
+fn menu_button_visuals(
+    menu_query: Query<(&Menu, &Children)>,
+    button_query: Query<(&MenuButton, &Interaction, &Children)>,
+    mut image_query: Query<(&mut UiImage, &mut UiColor)>,
+    ui_assets: Res<UIAssets>,
+) {
+    for (menu, children) in menu_query.iter() {
+        for child in children.iter() {
+            if let Ok((button, interaction, button_children)) = button_query.get(*child) {
+                let image_entity = button_children.iter().next().unwrap();
+                let (mut image, mut color) = image_query.get_mut(*image_entity).unwrap();
+                image.0 = if *interaction == Interaction::Clicked && button.active {
+                    ui_assets.button_pressed.clone()
+                } else {
+                    ui_assets.button.clone()
+                };
+                *color = if button.active && button.index == menu.focused {
+                    FOCUSED_TINT.into()
+                } else {
+                    Color::WHITE.into()
+                };
+            }
+        }
+    }
+}
+
+//A click or the confirm input on the focused button. Confirming is consumed so a screen opened by the
+//selection doesn't see the same press
+fn menu_selection_system<A: Component + Copy>(
+    mut keys: ResMut<Input<KeyCode>>,
+    controller: Option<Res<Controller>>,
+    mut gamepad_buttons: ResMut<Input<GamepadButton>>,
+    menu_query: Query<(&Menu, &Children)>,
+    button_query: Query<(&MenuButton, &A, &Interaction, ChangeTrackers<Interaction>)>,
+    mut send_selected: EventWriter<MenuSelected<A>>,
+    mut send_sound: EventWriter<SoundEvent>,
+) {
+    for (menu, children) in menu_query.iter() {
+        for child in children.iter() {
+            if let Ok((button, action, interaction, interaction_tracker)) = button_query.get(*child)
+            {
+                if !button.active {
+                    continue;
+                }
+                if *interaction == Interaction::Clicked && interaction_tracker.is_changed() {
+                    send_selected.send(MenuSelected(*action));
+                    send_sound.send(SoundEvent::new("menu_click"));
+                    continue;
+                }
+                if button.index != menu.focused {
+                    continue;
+                }
+                let confirm_pressed = controller.as_ref().is_some_and(|controller| {
+                    gamepad_buttons.clear_just_pressed(GamepadButton::new(
+                        controller.0,
+                        GamepadButtonType::South,
+                    ))
+                });
+                if keys.clear_just_pressed(KeyCode::Return) || confirm_pressed {
+                    send_selected.send(MenuSelected(*action));
+                    send_sound.send(SoundEvent::new("menu_click"));
+                }
+            }
+        }
+    }
+}
@@ -0,0 +1,259 @@
+use std::collections::HashMap;
+use std::f32::consts::FRAC_PI_2;
+use std::fs;
+
+use bevy::audio::AudioSink;
+use bevy::prelude::*;
+use serde::Deserialize;
+
+use crate::components::{Dead, Enemy, Player};
+use crate::constants::{
+    MAX_BLASTER_HEAT, MUSIC_CROSSFADE_SECONDS, MUSIC_DAMAGE_CAP, MUSIC_DAMAGE_DECAY,
+    MUSIC_DAMAGE_WEIGHT, MUSIC_ENEMY_CAP, MUSIC_ENEMY_WEIGHT, MUSIC_HEAT_WEIGHT,
+    MUSIC_PAUSED_VOLUME,
+};
+use crate::projectile_collision::LivingBeingHitEvent;
+use crate::resources::BlasterHeat;
+use crate::settings::Settings;
+use crate::spawn_manager::NewRoundEvent;
+use crate::states::GameState;
+
+//Layered soundtrack for a run. The stems in assets/music.ron all loop in step and get crossfaded by an
+//intensity worked out from how many enemies are up, how much damage the player has taken lately and how
+//hot the blaster is, with stingers on top for rounds starting and clearing and the game ending
+pub struct MusicPlugin;
+
+impl Plugin for MusicPlugin {
+    fn build(&self, app: &mut App) {
+        app.insert_resource(MusicIntensity::default())
+            .add_startup_system(load_music)
+            .add_system_set(
+                SystemSet::on_enter(GameState::MainGame).with_system(start_music_system),
+            )
+            .add_system_set(SystemSet::on_exit(GameState::MainGame).with_system(stop_music_system))
+            .add_system_set(SystemSet::on_update(GameState::MainGame).with_system(intensity_system))
+            .add_system_set(
+                SystemSet::on_enter(GameState::Intermission).with_system(round_clear_system),
+            )
+            .add_system_set(SystemSet::on_enter(GameState::GameOver).with_system(game_over_system))
+            .add_system(round_start_system)
+            .add_system(crossfade_system);
+    }
+}
+
+const MUSIC_PATH: &str = "assets/music.ron";
+
+#[derive(Deserialize)]
+struct StemDescriptor {
+    file: String,
+    intensity: f32,
+}
+
+#[derive(Deserialize)]
+struct MusicDescriptor {
+    stems: Vec<StemDescriptor>,
+    stingers: HashMap<String, String>,
+}
+
+struct Stem {
+    source: Handle<AudioSource>,
+    intensity: f32,
+    //Only while a run is going
+    sink: Option<Handle<AudioSink>>,
+    //Where the crossfade has got to, before the settings volume is applied
+    volume: f32,
+}
+
+struct Music {
+    //Quietest to most intense
+    stems: Vec<Stem>,
+    stingers: HashMap<String, Handle<AudioSource>>,
+}
+
+//0 when nothing is going on up to 1 when the player is swarmed, hurt and overheating
+#[derive(Default)]
+pub struct MusicIntensity {
+    pub value: f32,
+    //Damage taken, wearing off over time
+    recent_damage: f32,
+}
+
+//A broken music file is a bug in the assets, same as a broken sounds file
+fn load_music(mut commands: Commands, asset_server: Res<AssetServer>) {
+    let contents = fs::read_to_string(MUSIC_PATH)
+        .unwrap_or_else(|err| panic!("Couldn't read {}: {}", MUSIC_PATH, err));
+    let descriptor: MusicDescriptor = ron::from_str(&contents)
+        .unwrap_or_else(|err| panic!("Couldn't load {}: {}", MUSIC_PATH, err));
+    if descriptor.stems.is_empty() {
+        panic!("{} has no stems", MUSIC_PATH);
+    }
+
+    let mut stems: Vec<Stem> = descriptor
+        .stems
+        .into_iter()
+        .map(|stem| Stem {
+            source: asset_server.load(stem.file.as_str()),
+            intensity: stem.intensity,
+            sink: None,
+            volume: 0.0,
+        })
+        .collect();
+    stems.sort_by(|a, b| a.intensity.total_cmp(&b.intensity));
+    let stingers = descriptor
+        .stingers
+        .into_iter()
+        .map(|(name, file)| (name, asset_server.load(file.as_str())))
+        .collect();
+    commands.insert_resource(Music { stems, stingers });
+}
+
+//Every stem starts silent on the same frame so they stay in time with each other, the crossfade brings
+//the right one up from there
+fn start_music_system(
+    audio: Res<Audio>,
+    audio_sinks: Res<Assets<AudioSink>>,
+    mut music: ResMut<Music>,
+    mut intensity: ResMut<MusicIntensity>,
+) {
+    *intensity = MusicIntensity::default();
+    for stem in music.stems.iter_mut() {
+        let sink =
+            audio.play_with_settings(stem.source.clone(), PlaybackSettings::LOOP.with_volume(0.0));
+        //The handle handed back is weak and a looping sink that's let go of carries on forever
+        stem.sink = Some(audio_sinks.get_handle(sink));
+        stem.volume = 0.0;
+    }
+}
+
+fn stop_music_system(audio_sinks: Res<Assets<AudioSink>>, mut music: ResMut<Music>) {
+    for stem in music.stems.iter_mut() {
+        if let Some(sink) = stem.sink.take().and_then(|sink| audio_sinks.get(&sink)) {
+            sink.stop();
+        }
+    }
+}
+
+fn intensity_system(
+    time: Res<Time>,
+    blaster_heat: Res<BlasterHeat>,
+    mut intensity: ResMut<MusicIntensity>,
+    mut hit_events: EventReader<LivingBeingHitEvent>,
+    player_query: Query<(), With<Player>>,
+    enemy_query: Query<(), (With<Enemy>, Without<Dead>)>,
+) {
+    intensity.recent_damage =
+        (intensity.recent_damage - MUSIC_DAMAGE_DECAY * time.delta_seconds()).max(0.0);
+    for event in hit_events.iter() {
+        if player_query.get(event.entity).is_ok() {
+            intensity.recent_damage += event.damage as f32;
+        }
+    }
+
+    let enemies = (enemy_query.iter().count() as f32 / MUSIC_ENEMY_CAP).min(1.0);
+    let damage = (intensity.recent_damage / MUSIC_DAMAGE_CAP).min(1.0);
+    let heat = if blaster_heat.overheat_cooldown_timer.ready() {
+        (blaster_heat.value / MAX_BLASTER_HEAT).clamp(0.0, 1.0)
+    } else {
+        1.0
+    };
+    intensity.value =
+        (enemies * MUSIC_ENEMY_WEIGHT + damage * MUSIC_DAMAGE_WEIGHT + heat * MUSIC_HEAT_WEIGHT)
+            .min(1.0);
+}
+
+//How loud each stem should be at this intensity. Between two stems it's an equal power crossfade so the
+//mix doesn't dip in the middle
+fn stem_weights(stems: &[Stem], intensity: f32) -> Vec<f32> {
+    let mut weights = vec![0.0; stems.len()];
+    let above = stems
+        .iter()
+        .position(|stem| stem.intensity > intensity)
+        .unwrap_or(stems.len());
+    if above == 0 {
+        weights[0] = 1.0;
+    } else if above == stems.len() {
+        weights[above - 1] = 1.0;
+    } else {
+        let (low, high) = (&stems[above - 1], &stems[above]);
+        let t = (intensity - low.intensity) / (high.intensity - low.intensity);
+        weights[above - 1] = (t * FRAC_PI_2).cos();
+        weights[above] = (t * FRAC_PI_2).sin();
+    }
+    weights
+}
+
+//Runs in every state so the music ducks under the pause menu and fades out on the game over screen.
+//The settings volume is applied every frame so changing it from the pause menu is heard straight away
+fn crossfade_system(
+    time: Res<Time>,
+    state: Res<State<GameState>>,
+    settings: Res<Settings>,
+    intensity: Res<MusicIntensity>,
+    audio_sinks: Res<Assets<AudioSink>>,
+    mut music: ResMut<Music>,
+) {
+    let bus = settings.music_volume()
+        * match state.current() {
+            GameState::MainGame | GameState::Intermission => 1.0,
+            GameState::GameOver | GameState::NameEntry => 0.0,
+            _ => MUSIC_PAUSED_VOLUME,
+        };
+    let weights = stem_weights(&music.stems, intensity.value);
+    let step = time.delta_seconds() / MUSIC_CROSSFADE_SECONDS;
+    for (stem, weight) in music.stems.iter_mut().zip(weights) {
+        stem.volume += (weight - stem.volume).clamp(-step, step);
+        //The sink only turns up once its audio has loaded
+        if let Some(sink) = stem.sink.as_ref().and_then(|sink| audio_sinks.get(sink)) {
+            sink.set_volume(stem.volume * bus);
+        }
+    }
+}
+
+fn play_stinger(audio: &Audio, music: &Music, settings: &Settings, name: &str) {
+    match music.stingers.get(name) {
+        Some(stinger) => {
+            audio.play_with_settings(
+                stinger.clone(),
+                PlaybackSettings::ONCE.with_volume(settings.music_volume()),
+            );
+        }
+        None => warn!("No {} stinger in {}", name, MUSIC_PATH),
+    }
+}
+
+fn round_start_system(
+    audio: Res<Audio>,
+    music: Res<Music>,
+    settings: Res<Settings>,
+    mut new_round_events: EventReader<NewRoundEvent>,
+) {
+    if new_round_events.iter().count() > 0 {
+        play_stinger(&audio, &music, &settings, "round_start");
+    }
+}
+
+//The fight's over so the calm stem comes back in while the stats are up
+fn round_clear_system(
+    audio: Res<Audio>,
+    music: Res<Music>,
+    settings: Res<Settings>,
+    mut intensity: ResMut<MusicIntensity>,
+) {
+    *intensity = MusicIntensity::default();
+    play_stinger(&audio, &music, &settings, "round_clear");
+}
+
+//The game also ends by clearing the final round, which gets the round clear stinger instead
+fn game_over_system(
+    audio: Res<Audio>,
+    music: Res<Music>,
+    settings: Res<Settings>,
+    player_query: Query<(), (With<Player>, With<Dead>)>,
+) {
+    let stinger = if player_query.is_empty() {
+        "round_clear"
+    } else {
+        "game_over"
+    };
+    play_stinger(&audio, &music, &settings, stinger);
+}
@@ -0,0 +1,452 @@
+use std::collections::HashMap;
+use std::fs;
+use std::sync::Arc;
+
+use bevy::prelude::*;
+use rand::Rng;
+use serde::Deserialize;
+
+use crate::blaster::{BlasterFiredEvent, OverheatEvent};
+use crate::components::{Background, Dead, Player};
+use crate::projectile_collision::LivingBeingHitEvent;
+use crate::states::GameState;
+
+//Small CPU particle effects: muzzle flashes, impacts, explosions and steam off an overheated blaster.
+//Effects are described in assets/effects.ron and started with an EffectEvent, finished particles are
+//hidden and kept per effect so a big fight reuses them instead of spawning new ones every frame
+pub struct ParticlePlugin;
+
+impl Plugin for ParticlePlugin {
+    fn build(&self, app: &mut App) {
+        app.add_event::<EffectEvent>()
+            .insert_resource(PendingParticles(Vec::new()))
+            .insert_resource(ParticlePool(HashMap::new()))
+            .add_startup_system(load_effects)
+            .add_system_set(
+                SystemSet::on_update(GameState::MainGame)
+                    .with_system(shot_effects_system)
+                    .with_system(impact_effects_system)
+                    .with_system(death_effects_system)
+                    .with_system(overheat_effects_system)
+                    .with_system(start_effects_system.label(StartEffectsLabel))
+                    .with_system(emitter_system.label(StartEffectsLabel))
+                    .with_system(particle_system.after(StartEffectsLabel)),
+            );
+    }
+}
+
+#[derive(SystemLabel, Debug, Clone, PartialEq, Eq, Hash)]
+struct StartEffectsLabel;
+
+const EFFECTS_PATH: &str = "assets/effects.ron";
+
+pub struct EffectEvent {
+    pub name: String,
+    pub position: Vec2,
+    //Particles fan out around this, it doesn't need to be normalised
+    pub direction: Vec2,
+    //Continuous effects stick to this entity until they run out or it goes away
+    pub follow: Option<Entity>,
+}
+
+#[derive(Deserialize)]
+enum EmitterMode {
+    Burst(u32),
+    Continuous { rate: f32, seconds: f32 },
+}
+
+#[derive(Deserialize)]
+struct SheetDescriptor {
+    texture: String,
+    tile_size: (f32, f32),
+    columns: usize,
+    rows: usize,
+}
+
+#[derive(Deserialize)]
+struct EffectDescriptor {
+    #[serde(default)]
+    sheet: Option<SheetDescriptor>,
+    mode: EmitterMode,
+    lifetime: (f32, f32),
+    speed: (f32, f32),
+    #[serde(default)]
+    spread_degrees: f32,
+    size: (f32, f32),
+    colors: Vec<(f32, f32, f32, f32)>,
+    #[serde(default)]
+    drag: f32,
+}
+
+struct Effect {
+    name: String,
+    descriptor: EffectDescriptor,
+    atlas: Option<(Handle<TextureAtlas>, usize)>,
+}
+
+impl Effect {
+    fn color_at(&self, t: f32) -> Color {
+        let colors = &self.descriptor.colors;
+        if colors.is_empty() {
+            return Color::WHITE;
+        }
+        let position = t.clamp(0.0, 1.0) * (colors.len() - 1) as f32;
+        let index = (position as usize).min(colors.len() - 1);
+        let from = colors[index];
+        let to = colors[(index + 1).min(colors.len() - 1)];
+        let blend = position - index as f32;
+        let lerp = |a: f32, b: f32| a + (b - a) * blend;
+        Color::rgba(
+            lerp(from.0, to.0),
+            lerp(from.1, to.1),
+            lerp(from.2, to.2),
+            lerp(from.3, to.3),
+        )
+    }
+
+    fn size_at(&self, t: f32) -> Vec2 {
+        let (start, end) = self.descriptor.size;
+        Vec2::splat(start + (end - start) * t)
+    }
+}
+
+struct Effects(HashMap<String, Arc<Effect>>);
+
+struct PendingParticle {
+    effect: Arc<Effect>,
+    position: Vec2,
+    velocity: Vec2,
+    lifetime: f32,
+}
+
+//Particles asked for this frame, spawned or pulled out of the pool by particle_system
+struct PendingParticles(Vec<PendingParticle>);
+
+//Hidden particles by effect name, so a reused entity already has the right kind of sprite
+struct ParticlePool(HashMap<String, Vec<Entity>>);
+
+#[derive(Component)]
+struct Particle {
+    effect: Arc<Effect>,
+    velocity: Vec2,
+    timer: Timer,
+}
+
+#[derive(Component)]
+struct Emitter {
+    effect: Arc<Effect>,
+    position: Vec2,
+    direction: Vec2,
+    follow: Option<Entity>,
+    timer: Timer,
+    //Fractions of a particle carried over between frames
+    owed: f32,
+}
+
+//A broken effects file is a bug in the assets, same as a broken sprite sheet
+fn load_effects(
+    mut commands: Commands,
+    asset_server: Res<AssetServer>,
+    mut texture_atlases: ResMut<Assets<TextureAtlas>>,
+) {
+    let contents = fs::read_to_string(EFFECTS_PATH)
+        .unwrap_or_else(|err| panic!("Couldn't read {}: {}", EFFECTS_PATH, err));
+    let descriptors: HashMap<String, EffectDescriptor> = ron::from_str(&contents)
+        .unwrap_or_else(|err| panic!("Couldn't load {}: {}", EFFECTS_PATH, err));
+
+    let effects = descriptors
+        .into_iter()
+        .map(|(name, descriptor)| {
+            let atlas = descriptor.sheet.as_ref().map(|sheet| {
+                let atlas = TextureAtlas::from_grid(
+                    asset_server.load(&sheet.texture),
+                    Vec2::new(sheet.tile_size.0, sheet.tile_size.1),
+                    sheet.columns,
+                    sheet.rows,
+                );
+                (texture_atlases.add(atlas), sheet.columns * sheet.rows)
+            });
+            let effect = Effect {
+                name: name.clone(),
+                descriptor,
+                atlas,
+            };
+            (name, Arc::new(effect))
+        })
+        .collect();
+    commands.insert_resource(Effects(effects));
+}
+
+fn shot_effects_system(
+    mut fired_events: EventReader<BlasterFiredEvent>,
+    mut send_effect: EventWriter<EffectEvent>,
+) {
+    for event in fired_events.iter() {
+        send_effect.send(EffectEvent {
+            name: if event.power_shot {
+                "power_muzzle_flash".to_string()
+            } else {
+                "muzzle_flash".to_string()
+            },
+            position: event.position + event.direction.normalize_or_zero() * 20.0,
+            direction: event.direction,
+            follow: None,
+        });
+    }
+}
+
+fn impact_effects_system(
+    mut hit_events: EventReader<LivingBeingHitEvent>,
+    mut send_effect: EventWriter<EffectEvent>,
+) {
+    for event in hit_events.iter() {
+        for name in ["impact", "impact_sparks"] {
+            send_effect.send(EffectEvent {
+                name: name.to_string(),
+                position: event.position,
+                direction: Vec2::Y,
+                follow: None,
+            });
+        }
+    }
+}
+
+fn death_effects_system(
+    dead_query: Query<&Transform, Added<Dead>>,
+    mut send_effect: EventWriter<EffectEvent>,
+) {
+    for transform in dead_query.iter() {
+        send_effect.send(EffectEvent {
+            name: "explosion".to_string(),
+            position: transform.translation.truncate(),
+            direction: Vec2::Y,
+            follow: None,
+        });
+    }
+}
+
+fn overheat_effects_system(
+    mut overheat_events: EventReader<OverheatEvent>,
+    player_query: Query<Entity, With<Player>>,
+    mut send_effect: EventWriter<EffectEvent>,
+) {
+    for event in overheat_events.iter() {
+        send_effect.send(EffectEvent {
+            name: "overheat_steam".to_string(),
+            position: event.position,
+            direction: Vec2::Y,
+            follow: player_query.get_single().ok(),
+        });
+    }
+}
+
+fn queue_particle(
+    pending: &mut PendingParticles,
+    effect: &Arc<Effect>,
+    position: Vec2,
+    direction: Vec2,
+) {
+    let mut rng = rand::thread_rng();
+    let descriptor = &effect.descriptor;
+    let spread = descriptor.spread_degrees.to_radians();
+    let angle = direction
+        .normalize_or_zero()
+        .y
+        .atan2(direction.normalize_or_zero().x)
+        + rng.gen_range(-0.5..=0.5) * spread;
+    let speed = rng.gen_range(descriptor.speed.0..=descriptor.speed.1);
+    pending.0.push(PendingParticle {
+        effect: effect.clone(),
+        position,
+        velocity: Vec2::new(angle.cos(), angle.sin()) * speed,
+        lifetime: rng.gen_range(descriptor.lifetime.0..=descriptor.lifetime.1),
+    });
+}
+
+fn start_effects_system(
+    mut cmds: Commands,
+    effects: Res<Effects>,
+    mut pending: ResMut<PendingParticles>,
+    mut effect_events: EventReader<EffectEvent>,
+) {
+    for event in effect_events.iter() {
+        let effect = match effects.0.get(&event.name) {
+            Some(effect) => effect,
+            None => {
+                warn!("No effect called {} in {}", event.name, EFFECTS_PATH);
+                continue;
+            }
+        };
+        match effect.descriptor.mode {
+            EmitterMode::Burst(count) => {
+                for _ in 0..count {
+                    queue_particle(&mut pending, effect, event.position, event.direction);
+                }
+            }
+            EmitterMode::Continuous { seconds, .. } => {
+                cmds.spawn()
+                    .insert(Emitter {
+                        effect: effect.clone(),
+                        position: event.position,
+                        direction: event.direction,
+                        follow: event.follow,
+                        timer: Timer::from_seconds(seconds, false),
+                        owed: 0.0,
+                    })
+                    .insert(Background);
+            }
+        }
+    }
+}
+
+fn emitter_system(
+    mut cmds: Commands,
+    time: Res<Time>,
+    mut pending: ResMut<PendingParticles>,
+    mut emitter_query: Query<(Entity, &mut Emitter)>,
+    target_query: Query<&Transform, Without<Particle>>,
+) {
+    for (entity, mut emitter) in emitter_query.iter_mut() {
+        emitter.timer.tick(time.delta());
+        if let Some(target) = emitter.follow {
+            match target_query.get(target) {
+                Ok(transform) => emitter.position = transform.translation.truncate(),
+                Err(_) => {
+                    let duration = emitter.timer.duration();
+                    emitter.timer.set_elapsed(duration);
+                }
+            }
+        }
+        if emitter.timer.finished() {
+            cmds.entity(entity).despawn();
+            continue;
+        }
+
+        let rate = match emitter.effect.descriptor.mode {
+            EmitterMode::Continuous { rate, .. } => rate,
+            EmitterMode::Burst(_) => 0.0,
+        };
+        emitter.owed += rate * time.delta_seconds();
+        while emitter.owed >= 1.0 {
+            emitter.owed -= 1.0;
+            queue_particle(
+                &mut pending,
+                &emitter.effect,
+                emitter.position,
+                emitter.direction,
+            );
+        }
+    }
+}
+
+//Plain square particles have a Sprite, ones using a sheet have a TextureAtlasSprite
+type ParticleParts<'a> = (
+    Entity,
+    &'a mut Particle,
+    &'a mut Transform,
+    &'a mut Visibility,
+    Option<&'a mut Sprite>,
+    Option<&'a mut TextureAtlasSprite>,
+);
+
+fn particle_system(
+    mut cmds: Commands,
+    time: Res<Time>,
+    mut pending: ResMut<PendingParticles>,
+    mut pool: ResMut<ParticlePool>,
+    mut query: Query<ParticleParts>,
+) {
+    let delta = time.delta_seconds();
+    for (entity, mut particle, mut transform, mut visibility, sprite, atlas_sprite) in
+        query.iter_mut()
+    {
+        if !visibility.is_visible {
+            continue;
+        }
+        particle.timer.tick(time.delta());
+        if particle.timer.finished() {
+            visibility.is_visible = false;
+            pool.0
+                .entry(particle.effect.name.clone())
+                .or_default()
+                .push(entity);
+            continue;
+        }
+
+        let drag = (1.0 - particle.effect.descriptor.drag * delta).max(0.0);
+        particle.velocity *= drag;
+        transform.translation += (particle.velocity * delta).extend(0.0);
+
+        let t = particle.timer.percent();
+        let color = particle.effect.color_at(t);
+        let size = particle.effect.size_at(t);
+        if let Some(mut sprite) = sprite {
+            sprite.color = color;
+            sprite.custom_size = Some(size);
+        }
+        if let Some(mut sprite) = atlas_sprite {
+            sprite.color = color;
+            sprite.custom_size = Some(size);
+            if let Some((_, frames)) = particle.effect.atlas {
+                sprite.index = ((t * frames as f32) as usize).min(frames.saturating_sub(1));
+            }
+        }
+    }
+
+    for new in pending.0.drain(..) {
+        let effect = new.effect;
+        let transform = Transform::from_translation(new.position.extend(5.0));
+        let color = effect.color_at(0.0);
+        let size = Some(effect.size_at(0.0));
+        let particle = Particle {
+            effect: effect.clone(),
+            velocity: new.velocity,
+            timer: Timer::from_seconds(new.lifetime, false),
+        };
+
+        //Pooled particles go away with the rest of the session, so skip any that no longer exist
+        let pooled = pool.0.get_mut(&effect.name).and_then(|free| {
+            std::iter::from_fn(|| free.pop()).find(|entity| query.contains(*entity))
+        });
+        if let Some(entity) = pooled {
+            let (_, mut old, mut old_transform, mut visibility, sprite, atlas_sprite) =
+                query.get_mut(entity).unwrap();
+            *old = particle;
+            *old_transform = transform;
+            visibility.is_visible = true;
+            if let Some(mut sprite) = sprite {
+                sprite.color = color;
+                sprite.custom_size = size;
+            }
+            if let Some(mut sprite) = atlas_sprite {
+                sprite.color = color;
+                sprite.custom_size = size;
+                sprite.index = 0;
+            }
+            continue;
+        }
+
+        let mut particle_entity = match &effect.atlas {
+            Some((atlas, _)) => cmds.spawn_bundle(SpriteSheetBundle {
+                sprite: TextureAtlasSprite {
+                    color,
+                    custom_size: size,
+                    ..default()
+                },
+                texture_atlas: atlas.clone(),
+                transform,
+                ..default()
+            }),
+            None => cmds.spawn_bundle(SpriteBundle {
+                sprite: Sprite {
+                    color,
+                    custom_size: size,
+                    ..default()
+                },
+                transform,
+                ..default()
+            }),
+        };
+        particle_entity.insert(particle).insert(Background);
+    }
+}
@@ -0,0 +1,150 @@
+use bevy::{ecs::schedule::ShouldRun, prelude::*};
+
+use crate::intermission::{pause_physics, resume_physics};
+use crate::menu::{MenuActionPlugin, MenuBuilder, MenuSelected, UIAssets};
+use crate::replay::ReplayPlayback;
+use crate::resources::Controller;
+use crate::states::GameState;
+use crate::stats::RoundStats;
+
+pub struct PausePlugin;
+
+impl Plugin for PausePlugin {
+    fn build(&self, app: &mut App) {
+        app.add_plugin(MenuActionPlugin::<PauseButton>::default())
+            .add_system_set(
+                SystemSet::on_update(GameState::MainGame).with_system(pause_input_system),
+            )
+            .add_system_set(
+                SystemSet::on_enter(GameState::Paused)
+                    .with_system(pause_physics)
+                    .with_system(setup_pause_menu),
+            )
+            .add_system_set(
+                SystemSet::on_update(GameState::Paused)
+                    .with_system(resume_input_system)
+                    .with_system(handle_pause_buttons),
+            )
+            //Settings are pushed on top of the pause menu and hand back to it when they're done
+            .add_system_set(SystemSet::on_pause(GameState::Paused).with_system(despawn_pause_menu))
+            .add_system_set(SystemSet::on_resume(GameState::Paused).with_system(setup_pause_menu))
+            .add_system_set(
+                SystemSet::on_exit(GameState::Paused)
+                    .with_system(despawn_pause_menu)
+                    .with_system(resume_physics),
+            );
+    }
+}
+
+//Sent when the player restarts the round from the pause menu, each plugin puts its own part of the
+//round back the way it was when the round started
+pub struct RestartRoundEvent {
+    pub score: usize,
+}
+
+#[derive(Component)]
+struct PauseMenu;
+
+#[derive(Component, Clone, Copy)]
+enum PauseButton {
+    Resume,
+    RestartRound,
+    Settings,
+    QuitToMenu,
+}
+
+//Run criteria for anything that should freeze while the game is paused but keep going everywhere else
+pub fn not_paused(state: Res<State<GameState>>) -> ShouldRun {
+    if *state.current() == GameState::Paused {
+        ShouldRun::No
+    } else {
+        ShouldRun::Yes
+    }
+}
+
+//Consumes the press, otherwise the state we just switched to sees it on the same frame and switches straight back
+fn pause_pressed(
+    keys: &mut Input<KeyCode>,
+    controller: Option<Res<Controller>>,
+    buttons: &mut Input<GamepadButton>,
+) -> bool {
+    let start_button =
+        controller.map(|controller| GamepadButton::new(controller.0, GamepadButtonType::Start));
+    let start_pressed =
+        start_button.is_some_and(|start_button| buttons.clear_just_pressed(start_button));
+    keys.clear_just_pressed(KeyCode::Escape) || start_pressed
+}
+
+//A replay has its own pause and nothing to restart or change, so the pause button just leaves it
+fn pause_input_system(
+    mut keys: ResMut<Input<KeyCode>>,
+    controller: Option<Res<Controller>>,
+    mut buttons: ResMut<Input<GamepadButton>>,
+    mut state: ResMut<State<GameState>>,
+    replay: Res<ReplayPlayback>,
+) {
+    if pause_pressed(&mut keys, controller, &mut buttons) {
+        if replay.is_playing() {
+            let _ = state.replace(GameState::MainMenu);
+        } else {
+            let _ = state.push(GameState::Paused);
+        }
+    }
+}
+
+fn resume_input_system(
+    mut keys: ResMut<Input<KeyCode>>,
+    controller: Option<Res<Controller>>,
+    mut buttons: ResMut<Input<GamepadButton>>,
+    mut state: ResMut<State<GameState>>,
+) {
+    if pause_pressed(&mut keys, controller, &mut buttons) {
+        let _ = state.pop();
+    }
+}
+
+fn handle_pause_buttons(
+    mut selected: EventReader<MenuSelected<PauseButton>>,
+    mut state: ResMut<State<GameState>>,
+    mut send_restart: EventWriter<RestartRoundEvent>,
+    round_stats: Res<RoundStats>,
+) {
+    for MenuSelected(action) in selected.iter() {
+        match action {
+            PauseButton::Resume => {
+                let _ = state.pop();
+            }
+            PauseButton::RestartRound => {
+                send_restart.send(RestartRoundEvent {
+                    score: round_stats.score_at_start,
+                });
+                let _ = state.pop();
+            }
+            PauseButton::Settings => {
+                let _ = state.push(GameState::ControlMenu);
+            }
+            //Unwinds the whole state stack, the session cleans up after itself when MainGame exits
+            PauseButton::QuitToMenu => {
+                let _ = state.replace(GameState::MainMenu);
+            }
+        }
+    }
+}
+
+fn setup_pause_menu(mut commands: Commands, ui_assets: Res<UIAssets>) {
+    MenuBuilder::new()
+        .title("Paused")
+        .backdrop(Color::rgba(0.0, 0.0, 0.0, 0.6))
+        .button("Resume", PauseButton::Resume)
+        .button("Restart Round", PauseButton::RestartRound)
+        .button("Settings", PauseButton::Settings)
+        .button("Quit to Menu", PauseButton::QuitToMenu)
+        .spawn(&mut commands, &ui_assets)
+        .insert(PauseMenu);
+}
+
+fn despawn_pause_menu(mut commands: Commands, query: Query<Entity, With<PauseMenu>>) {
+    for entity in query.iter() {
+        commands.entity(entity).despawn_recursive();
+    }
+}
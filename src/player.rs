@@ -7,35 +7,47 @@ use bevy_rapier2d::prelude::*;
 use bevy_rapier2d::rapier::prelude::CollisionEventFlags;
 use nalgebra::{vector, Vector2};
 
-use crate::blaster::BlasterFiredEvent;
+use crate::blaster::{BlasterFiredEvent, OverheatEvent};
 use crate::components::{
-    AnimationTimer, Dead, Direction, Dispose, Enemy, Health, Lives, LivingBeing, Player, WeaponData,
+    Dead, Direction, Dispose, Enemy, Health, Lives, LivingBeing, Player, WeaponData,
 };
 use crate::constants::*; //Should probably fix this, it's a little lazy
+use crate::controls::{PlayerInput, ReadInputLabel};
 use crate::debug;
+use crate::fixed_step::{FixedStepApp, FixedSystems, FixedTime};
+use crate::graphics::{AnimationEvent, Animator, SpriteSheets};
+use crate::pause::RestartRoundEvent;
 use crate::projectile_collision::{KnockBackEvent, LivingBeingDeathEvent, LivingBeingHitEvent};
-use crate::resources::{BlasterHeat, Controller, GameTextures, PlayerLives, WindowSize};
-use crate::states::{GameState, PlayerAnimationInfo, PlayerState, SpriteLocation};
+use crate::resources::{BlasterHeat, GameTextures, PlayerLives};
+use crate::states::GameState;
 use crate::utils::CooldownTimer;
 
 pub struct PlayerPlugin;
 
+pub struct LifeLostEvent {
+    pub lives_remaining: u32,
+}
+
 impl Plugin for PlayerPlugin {
     fn build(&self, app: &mut App) {
-        app.add_event::<LivingBeingHitEvent>()
-            .add_event::<LivingBeingDeathEvent>()
-            .add_event::<BlasterFiredEvent>()
-            .add_event::<KnockBackEvent>()
+        app.insert_resource(PlayerInput::default())
+            .add_fixed_event::<LivingBeingHitEvent>()
+            .add_fixed_event::<LivingBeingDeathEvent>()
+            .add_fixed_event::<BlasterFiredEvent>()
+            .add_fixed_event::<KnockBackEvent>()
+            .add_fixed_event::<OverheatEvent>()
+            .add_fixed_event::<LifeLostEvent>()
             .add_system_set(
                 SystemSet::on_enter(GameState::MainGame).with_system(player_spawn_system),
             )
-            .add_system_set(
-                SystemSet::on_update(GameState::MainGame)
-                    .with_system(player_move_system)
-                    .with_system(player_fire_aim_system)
+            .add_fixed_systems(
+                FixedSystems::on_update(GameState::MainGame)
+                    .with_system(player_move_system.after(ReadInputLabel))
+                    .with_system(player_fire_aim_system.after(ReadInputLabel))
                     .with_system(collision_with_enemy)
                     .with_system(display_lives_ui)
-                    .with_system(player_dying),
+                    .with_system(player_dying)
+                    .with_system(restart_round_system),
             );
     }
 }
@@ -43,20 +55,16 @@ impl Plugin for PlayerPlugin {
 fn player_spawn_system(
     mut cmds: Commands,
 
-    asset_server: Res<AssetServer>,
-    mut texture_atlases: ResMut<Assets<TextureAtlas>>,
+    sprite_sheets: Res<SpriteSheets>,
     mut rapier_config: ResMut<RapierConfiguration>,
 ) {
     rapier_config.gravity = Vec2::ZERO;
 
-    let texture_handle =
-        asset_server.load("darians-assets/TeamGunner/CHARACTER_SPRITES/Blue/Blue_Soldier_50.png");
-    let texture_atlas = TextureAtlas::from_grid(texture_handle, Vec2::new(50.0, 50.0), 8, 5);
-    let texture_atlas_handle = texture_atlases.add(texture_atlas);
+    let sheet = sprite_sheets.get("player");
 
     // Add the player sprite
     let sprite = SpriteSheetBundle {
-        texture_atlas: texture_atlas_handle,
+        texture_atlas: sheet.atlas.clone(),
         transform: Transform::from_scale(Vec3::splat(PLAYER_SPRITE_SCALE)),
         ..default()
     };
@@ -81,7 +89,7 @@ fn player_spawn_system(
             (PLAYER_GROUP | CIVILIAN_GROUP | PHYSICAL_GROUP),
         ))
         //Custom functionality
-        .insert(AnimationTimer(Timer::from_seconds(0.1, true)))
+        .insert(Animator::new(sheet))
         .insert(LivingBeing)
         .insert(Player)
         .insert(Health {
@@ -93,123 +101,54 @@ fn player_spawn_system(
         .insert(WeaponData {
             ..Default::default()
         })
-        .insert(PlayerAnimationInfo {
-            state: PlayerState::Idle,
-            is_flip: false,
-        })
         .insert(Direction { is_right: true });
 }
 
 fn player_move_system(
-    mut players: Query<(Entity, &mut Velocity, &Player, &mut PlayerAnimationInfo), Without<Dead>>,
-
-    controller: Option<Res<Controller>>,
-    axes: Res<Axis<GamepadAxis>>,
-    buttons: Res<Input<GamepadButton>>,
-
-    keys: Res<Input<KeyCode>>,
+    mut players: Query<(Entity, &mut Velocity, &Player, &mut Animator), Without<Dead>>,
+    input: Res<PlayerInput>,
 ) {
-    let mut is_jump = false;
-    let mut is_crouch = false;
-
-    let mut player_vel = Vec2::new(0.0, 0.0);
-
-    if let Some(controller) = controller {
-        let axis_lx = GamepadAxis::new(controller.0, GamepadAxisType::LeftStickX);
-        let axis_ly = GamepadAxis::new(controller.0, GamepadAxisType::LeftStickY);
-
-        if let (Some(x), Some(y)) = (axes.get(axis_lx), axes.get(axis_ly)) {
-            player_vel.x = x;
-            player_vel.y = y;
-        }
-    } else {
-        if keys.pressed(KeyCode::W) {
-            player_vel.y = 1.;
-        } else if keys.pressed(KeyCode::S) {
-            player_vel.y = -1.;
-        } else {
-            player_vel.y = 0.;
-        }
-        if keys.pressed(KeyCode::D) {
-            player_vel.x = 1.;
-        } else if keys.pressed(KeyCode::A) {
-            player_vel.x = -1.;
-        } else {
-            player_vel.x = 0.;
-        }
-        if keys.pressed(KeyCode::Space) {
-            is_jump = true;
-        }
-        if keys.pressed(KeyCode::C) {
-            is_crouch = true;
-        }
-    }
+    let player_vel = input.movement;
+    let is_jump = input.jump;
+    let is_crouch = input.crouch;
 
     //Break this out into a seperate system
     //Also should this just not be in a for loop????:
-    for (mut player_entity, mut velocity, player, mut player_state) in players.get_single_mut() {
+    for (mut player_entity, mut velocity, player, mut animator) in players.get_single_mut() {
         *velocity = Velocity::linear(player_vel * PLAYER_SPEED);
         if (velocity.linvel.x < 0.0) {
-            player_state.is_flip = true;
+            animator.facing_left = true;
         } else if (velocity.linvel.x > 0.0) {
-            player_state.is_flip = false;
+            animator.facing_left = false;
         }
 
-        if (velocity.linvel == Vec2 { x: 0.0, y: 0.0 }) {
-            player_state.state = PlayerState::Idle;
-        } else {
-            player_state.state = PlayerState::Run;
-        }
         if (is_jump) {
-            player_state.state = PlayerState::Jump;
+            animator.play("jump");
         } else if (is_crouch) {
-            player_state.state = PlayerState::Crouch;
+            animator.play("crouch");
+        } else if (velocity.linvel == Vec2 { x: 0.0, y: 0.0 }) {
+            animator.play("idle");
+        } else {
+            animator.play("run");
         }
     }
 }
 
 fn player_fire_aim_system(
     mut cmds: Commands,
-    time: Res<Time>,
+    time: Res<FixedTime>,
     mut blaster_heat: ResMut<BlasterHeat>,
 
     mut player: Query<(Entity, &Transform, &mut WeaponData, &mut Direction), (With<Player>)>,
 
     mut send_fire_event: EventWriter<BlasterFiredEvent>,
+    mut send_overheat_event: EventWriter<OverheatEvent>,
 
-    controller: Option<Res<Controller>>,
-    axes: Res<Axis<GamepadAxis>>,
-    buttons: Res<Input<GamepadButton>>,
-
-    mouse_buttons: Res<Input<MouseButton>>,
-    win_size: Res<WindowSize>,
-    windows: Res<Windows>,
+    input: Res<PlayerInput>,
 ) {
     let (player, player_tf, mut weapon, mut player_dir) = player.get_single_mut().unwrap();
-    let mut weapon_dir = Vec2::new(0.0, 0.0);
-    let window = windows.get_primary().unwrap();
-
-    if let Some(cursor) = window.cursor_position() {
-        weapon_dir = Vec2::new(
-            cursor.x - win_size.w / 2.0 - player_tf.translation.x,
-            cursor.y - win_size.h / 2.0 - player_tf.translation.y,
-        );
-    }
-
-    weapon.firing = mouse_buttons.pressed(MouseButton::Left);
-
-    if let Some(controller) = controller {
-        let normal_fire_button = GamepadButton::new(controller.0, GamepadButtonType::LeftTrigger);
-        weapon.firing = buttons.pressed(normal_fire_button);
-
-        let axis_rx = GamepadAxis::new(controller.0, GamepadAxisType::RightStickX);
-        let axis_ry = GamepadAxis::new(controller.0, GamepadAxisType::RightStickY);
-        if let (Some(x), Some(y)) = (axes.get(axis_rx), axes.get(axis_ry)) {
-            if x.abs() > 0.2 || y.abs() > 0.2 {
-                weapon_dir = Vec2::new(x, y);
-            }
-        }
-    }
+    let weapon_dir = input.aim;
+    weapon.firing = input.firing;
 
     weapon.fire_rate_timer.tick(time.delta());
     blaster_heat.overheat_cooldown_timer.tick(time.delta());
@@ -217,6 +156,11 @@ fn player_fire_aim_system(
         0f32.max(blaster_heat.value - (time.delta_seconds() * BLASTER_COOLOFF_MULTIPLIER));
 
     if blaster_heat.value >= MAX_BLASTER_HEAT && !debug::is_overheat_disabled() {
+        if blaster_heat.overheat_cooldown_timer.ready() {
+            send_overheat_event.send(OverheatEvent {
+                position: player_tf.translation.truncate(),
+            });
+        }
         blaster_heat.overheat_cooldown_timer.trigger();
     }
 
@@ -225,8 +169,9 @@ fn player_fire_aim_system(
         && blaster_heat.overheat_cooldown_timer.ready()
     {
         weapon.fire_rate_timer.trigger();
+        //Running the blaster close to overheating is rewarded with stronger shots
+        let power_shot = blaster_heat.value >= BLASTER_POWER_SHOT_THRESHOLD;
         blaster_heat.value += BLASTER_SHOT_HEAT_ADDITION;
-        println!("Blaster Temp: {} C", blaster_heat.value);
 
         let event = BlasterFiredEvent {
             position: Vec2::new(player_tf.translation.x, player_tf.translation.y),
@@ -234,7 +179,8 @@ fn player_fire_aim_system(
             from_player: true,
             memberships: ENEMY_GROUP,
             filter: ENEMY_GROUP,
-            color: Color::rgb(0.0, 0.0, 1.0),
+            projectile: weapon.projectile,
+            power_shot,
         };
         send_fire_event.send(event);
     }
@@ -295,30 +241,32 @@ pub fn display_lives_ui(
 //commands.entity(entity).remove::<Component>()
 fn player_dying(
     mut player_query: Query<
-        (
-            Entity,
-            &mut PlayerAnimationInfo,
-            &mut Dead,
-            &mut Lives,
-            &mut Health,
-        ),
+        (Entity, &mut Animator, &mut Dead, &mut Lives, &mut Health),
         (With<(Player)>, Without<Dispose>),
     >,
     mut commands: Commands,
-    time: Res<Time>,
+    mut animation_events: EventReader<AnimationEvent>,
     mut state: ResMut<State<GameState>>,
+    mut send_life_lost: EventWriter<LifeLostEvent>,
 ) {
-    for (player, mut player_state, mut dead, mut lives, mut health) in player_query.iter_mut() {
-        player_state.state = PlayerState::Death;
+    for (player, mut animator, mut dead, mut lives, mut health) in player_query.iter_mut() {
+        animator.play("death");
         if (!dead.dying) {
             dead.dying = true;
-            dead.time_till_dispose.trigger();
+            send_life_lost.send(LifeLostEvent {
+                lives_remaining: lives.lives_num,
+            });
         }
-        dead.time_till_dispose.tick(time.delta());
+    }
 
-        if (dead.time_till_dispose.ready()) {
+    //Respawn or game over once the death animation has played out
+    for event in animation_events.iter() {
+        if event.name != "dispose" {
+            continue;
+        }
+        if let Ok((player, _, _, mut lives, mut health)) = player_query.get_mut(event.entity) {
             if lives.lives_num == 0 {
-                state.push(GameState::GameOver).unwrap();
+                let _ = state.push(GameState::GameOver);
             } else {
                 lives.lives_num = lives.lives_num.saturating_sub(1);
                 health.health = PLAYER_HEALTH;
@@ -327,3 +275,35 @@ fn player_dying(
         }
     }
 }
+
+//Put the player back in the middle of the arena with a cool blaster, lives carry over from before the restart
+fn restart_round_system(
+    mut restart_events: EventReader<RestartRoundEvent>,
+    mut player_query: Query<
+        (
+            Entity,
+            &mut Transform,
+            &mut Velocity,
+            &mut Health,
+            &mut Animator,
+        ),
+        With<Player>,
+    >,
+    mut commands: Commands,
+    mut blaster_heat: ResMut<BlasterHeat>,
+) {
+    if restart_events.iter().last().is_none() {
+        return;
+    }
+
+    for (player, mut transform, mut velocity, mut health, mut animator) in player_query.iter_mut() {
+        transform.translation.x = 0.;
+        transform.translation.y = 0.;
+        *velocity = Velocity::zero();
+        health.health = PLAYER_HEALTH;
+        animator.play("idle");
+        commands.entity(player).remove::<Dead>();
+    }
+    blaster_heat.value = 0.;
+    blaster_heat.overheat_cooldown_timer = CooldownTimer::from_seconds(COOLDOWN_TIME_SECONDS);
+}
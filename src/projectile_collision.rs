@@ -2,16 +2,20 @@ use bevy::prelude::*;
 use bevy::sprite::collide_aabb::collide;
 use bevy_rapier2d::prelude::*;
 
-use crate::components::{Dead, Dispose, Enemy, FromPlayer, Health, Lives, LivingBeing, Player};
+use crate::components::{
+    Archetype, Dead, Dispose, Enemy, FromPlayer, Health, Lives, LivingBeing, Player,
+};
 use crate::constants::{KNOCKBACK_POWER, PLAYER_HEALTH};
+use crate::fixed_step::{FixedStepApp, FixedSystems};
+use crate::resources::SpawnType;
 use crate::states::GameState;
 use crate::utils::CooldownTimer;
 pub struct CollisionPlugin;
 
 impl Plugin for CollisionPlugin {
     fn build(&self, app: &mut App) {
-        app.add_system_set(
-            SystemSet::on_update(GameState::MainGame)
+        app.add_fixed_event::<EnemyKilledEvent>().add_fixed_systems(
+            FixedSystems::on_update(GameState::MainGame)
                 .with_system(on_living_being_hit)
                 .with_system(on_knock_back)
                 .with_system(despawn_dispose),
@@ -23,6 +27,16 @@ impl Plugin for CollisionPlugin {
 pub struct LivingBeingHitEvent {
     pub entity: Entity,
     pub damage: u32,
+    pub power_shot: bool,
+    //Where the shot landed
+    pub position: Vec2,
+}
+
+pub struct EnemyKilledEvent {
+    pub entity: Entity,
+    pub archetype: SpawnType,
+    pub position: Vec2,
+    pub power_shot: bool,
 }
 
 pub struct LivingBeingDeathEvent {
@@ -38,19 +52,30 @@ pub fn on_living_being_hit(
     mut commands: Commands,
     mut living_being_hit_events: EventReader<LivingBeingHitEvent>,
     mut send_living_being_death: EventWriter<LivingBeingDeathEvent>,
-    mut living_being: Query<(Entity, &mut Health), (With<LivingBeing>, Without<Dead>)>,
+    mut send_enemy_killed: EventWriter<EnemyKilledEvent>,
+    mut living_being: Query<
+        (Entity, &mut Health, &Transform, Option<&Archetype>),
+        (With<LivingBeing>, Without<Dead>),
+    >,
 ) {
     for event in living_being_hit_events.iter() {
-        for (being, mut health) in living_being.iter_mut() {
-            if (being == event.entity) {
-                health.health = health.health.saturating_sub(event.damage);
+        if let Ok((being, mut health, transform, archetype)) = living_being.get_mut(event.entity) {
+            //Several hits can land on the same frame, only the one that finishes the being off counts
+            if health.health == 0 {
+                continue;
             }
+            health.health = health.health.saturating_sub(event.damage);
 
             if health.health == 0 {
-                commands.entity(event.entity).insert(Dead {
-                    time_till_dispose: CooldownTimer::from_seconds(0.5),
-                    dying: false,
-                });
+                commands.entity(being).insert(Dead { dying: false });
+                if let Some(archetype) = archetype {
+                    send_enemy_killed.send(EnemyKilledEvent {
+                        entity: being,
+                        archetype: archetype.0,
+                        position: transform.translation.truncate(),
+                        power_shot: event.power_shot,
+                    });
+                }
             }
         }
     }
@@ -0,0 +1,610 @@
+use std::error::Error;
+use std::fs;
+use std::path::{Path, PathBuf};
+
+use bevy::app::AppExit;
+use bevy::ecs::system::SystemParam;
+use bevy::prelude::*;
+use serde::{Deserialize, Serialize};
+
+use crate::components::{GameUi, HudText};
+use crate::constants::*;
+use crate::controls::{PlayerInput, ReadInputLabel};
+use crate::fixed_step::{FixedStepApp, FixedSystems, FixedTime};
+use crate::pause::RestartRoundEvent;
+use crate::resources::{GameFont, GameMode, GameRng, WindowSize};
+use crate::rounds::{seed_run, RoundTracker};
+use crate::settings::{Difficulty, Settings};
+use crate::states::GameState;
+
+//Every run records the input it was played with. A run is its seed plus its input, so playing the input
+//back on the fixed step from the same seed plays the same run out again, tick for tick
+pub struct ReplayPlugin;
+
+impl Plugin for ReplayPlugin {
+    fn build(&self, app: &mut App) {
+        app.init_resource::<QueuedReplay>()
+            .init_resource::<ReplayPlayback>()
+            .insert_resource(ReplayRecorder::new(Some(Replay::last_run_path())))
+            .add_fixed_systems(
+                FixedSystems::on_update(GameState::MainGame)
+                    .with_system(playback_system.label(ReadInputLabel))
+                    .with_system(record_system.after(ReadInputLabel)),
+            )
+            .add_system_set(
+                SystemSet::on_update(GameState::MainMenu).with_system(start_queued_replay_system),
+            )
+            .add_system_set(
+                SystemSet::on_enter(GameState::MainGame)
+                    .with_system(start_recording_system)
+                    .with_system(spawn_replay_ui_system),
+            )
+            .add_system_set(
+                SystemSet::on_update(GameState::MainGame).with_system(replay_controls_system),
+            )
+            .add_system_set(
+                SystemSet::on_exit(GameState::MainGame)
+                    .with_system(save_recording_system)
+                    .with_system(stop_playback_system),
+            )
+            .add_system(update_replay_ui_system)
+            //Closing the game mid run still keeps the run
+            .add_system_to_stage(CoreStage::Last, save_recording_on_exit_system);
+    }
+}
+
+#[derive(Clone, Copy, Debug, Serialize, Deserialize)]
+enum ReplayEvent {
+    //Restarted from the pause menu, back to the score the round started with
+    RestartRound(usize),
+    //Changed in the settings part way through the run
+    Difficulty(Difficulty),
+    //The window was resized, which changes where things spawn
+    WindowSize(f32, f32),
+}
+
+//How a tick's input goes in the file: movement, aim, then the buttons as bits. A fraction of the size
+//PlayerInput's field names would take up, written out tens of thousands of times
+#[derive(Clone, Copy, PartialEq, Serialize, Deserialize)]
+struct TickInput(Vec2, Vec2, u8);
+
+const FIRING_BIT: u8 = 0b1;
+const JUMP_BIT: u8 = 0b10;
+const CROUCH_BIT: u8 = 0b100;
+
+impl From<PlayerInput> for TickInput {
+    fn from(input: PlayerInput) -> Self {
+        let mut buttons = 0;
+        for (pressed, bit) in [
+            (input.firing, FIRING_BIT),
+            (input.jump, JUMP_BIT),
+            (input.crouch, CROUCH_BIT),
+        ] {
+            if pressed {
+                buttons |= bit;
+            }
+        }
+        TickInput(input.movement, input.aim, buttons)
+    }
+}
+
+impl From<TickInput> for PlayerInput {
+    fn from(TickInput(movement, aim, buttons): TickInput) -> Self {
+        PlayerInput {
+            movement,
+            aim,
+            firing: buttons & FIRING_BIT != 0,
+            jump: buttons & JUMP_BIT != 0,
+            crouch: buttons & CROUCH_BIT != 0,
+        }
+    }
+}
+
+//A recorded run, written as compact ron. Everything the run depends on besides the game itself is in
+//the header: the seed, the mode, the settings and the window size, which spawn positions come from
+#[derive(Clone, Serialize, Deserialize)]
+pub struct Replay {
+    pub version: String,
+    pub seed: u64,
+    pub mode: GameMode,
+    pub settings: Settings,
+    pub window: (f32, f32),
+    //The input of every tick of MainGame, as runs of the same input repeated for so many ticks
+    inputs: Vec<(u32, TickInput)>,
+    //Anything besides the input that changed the run, by the tick it happened on
+    events: Vec<(u64, ReplayEvent)>,
+}
+
+impl Replay {
+    pub fn last_run_path() -> PathBuf {
+        dirs::data_dir()
+            .unwrap_or_else(|| PathBuf::from("."))
+            .join("my-blaster-runs-hot")
+            .join("last_run.replay")
+    }
+
+    //A replay from another version still loads, it just might not play out the way it did
+    pub fn load(path: &Path) -> Result<Self, Box<dyn Error>> {
+        let contents = fs::read_to_string(path)?;
+        let replay: Replay = ron::from_str(&contents)?;
+        if replay.version != env!("CARGO_PKG_VERSION") {
+            warn!(
+                "{} was recorded with version {}, this is {}, it may play out differently",
+                path.display(),
+                replay.version,
+                env!("CARGO_PKG_VERSION")
+            );
+        }
+        Ok(replay)
+    }
+
+    pub fn save(&self, path: &Path) -> Result<(), Box<dyn Error>> {
+        if let Some(parent) = path.parent() {
+            fs::create_dir_all(parent)?;
+        }
+        let contents = ron::to_string(self)?;
+        let temp_path = path.with_extension("replay.tmp");
+        fs::write(&temp_path, contents)?;
+        fs::rename(&temp_path, path)?;
+        Ok(())
+    }
+
+    //One input per tick, with the runs spelled out again
+    fn tick_inputs(&self) -> Vec<PlayerInput> {
+        self.inputs
+            .iter()
+            .flat_map(|(ticks, input)| (0..*ticks).map(move |_| PlayerInput::from(*input)))
+            .collect()
+    }
+
+    fn push_input(&mut self, input: PlayerInput) {
+        let input = TickInput::from(input);
+        match self.inputs.last_mut() {
+            Some((ticks, last)) if *last == input => *ticks += 1,
+            _ => self.inputs.push((1, input)),
+        }
+    }
+}
+
+//The run being recorded, and where it gets written when it's over. No path means it's thrown away
+pub struct ReplayRecorder {
+    path: Option<PathBuf>,
+    replay: Option<Replay>,
+    ticks: u64,
+    //As of the last tick, so a change in the settings or the window is recorded once
+    difficulty: Difficulty,
+    window: (f32, f32),
+}
+
+impl ReplayRecorder {
+    pub fn new(path: Option<PathBuf>) -> Self {
+        Self {
+            path,
+            replay: None,
+            ticks: 0,
+            difficulty: Difficulty::Normal,
+            window: (0.0, 0.0),
+        }
+    }
+
+    //Writes the run out, once, and stops recording
+    pub fn save(&mut self) {
+        if let (Some(path), Some(replay)) = (&self.path, self.replay.take()) {
+            if replay.inputs.is_empty() {
+                return;
+            }
+            match replay.save(path) {
+                Ok(()) => info!("Replay saved to {}", path.display()),
+                Err(err) => warn!("Couldn't save the replay to {}: {}", path.display(), err),
+            }
+        }
+    }
+}
+
+//A replay waiting on the main menu to start it, from the command line or the menu button
+#[derive(Default)]
+pub struct QueuedReplay(pub Option<Replay>);
+
+struct Playback {
+    inputs: Vec<PlayerInput>,
+    events: Vec<(u64, ReplayEvent)>,
+    tick: usize,
+    finished: bool,
+    //What the replay overrode, put back when it's over
+    player_difficulty: Difficulty,
+    player_window: (f32, f32),
+}
+
+//Some while a replay is playing
+#[derive(Default)]
+pub struct ReplayPlayback(Option<Playback>);
+
+impl ReplayPlayback {
+    pub fn is_playing(&self) -> bool {
+        self.0.is_some()
+    }
+
+    //The viewer's own window size while a replay is playing, put back once it's over
+    pub fn viewer_window_mut(&mut self) -> Option<&mut (f32, f32)> {
+        self.0.as_mut().map(|playback| &mut playback.player_window)
+    }
+}
+
+//Everything a replay sets up before its run starts
+#[derive(SystemParam)]
+pub struct ReplayedRun<'w, 's> {
+    playback: ResMut<'w, ReplayPlayback>,
+    game_mode: ResMut<'w, GameMode>,
+    round_tracker: ResMut<'w, RoundTracker>,
+    game_rng: ResMut<'w, GameRng>,
+    settings: ResMut<'w, Settings>,
+    win_size: ResMut<'w, WindowSize>,
+    #[system_param(ignore)]
+    _marker: std::marker::PhantomData<&'s ()>,
+}
+
+impl<'w, 's> ReplayedRun<'w, 's> {
+    //Only the difficulty is taken from the recorded settings, the rest are the viewer's own
+    fn start(&mut self, replay: Replay) {
+        *self.game_mode = replay.mode;
+        seed_run(
+            replay.seed,
+            replay.mode,
+            &mut self.round_tracker,
+            &mut self.game_rng,
+        );
+        let player_difficulty = self.settings.difficulty;
+        if self.settings.difficulty != replay.settings.difficulty {
+            self.settings.difficulty = replay.settings.difficulty;
+        }
+        let player_window = (self.win_size.w, self.win_size.h);
+        self.win_size.w = replay.window.0;
+        self.win_size.h = replay.window.1;
+        self.playback.0 = Some(Playback {
+            inputs: replay.tick_inputs(),
+            events: replay.events,
+            tick: 0,
+            finished: false,
+            player_difficulty,
+            player_window,
+        });
+    }
+}
+
+//For when there's no main menu to start it from, like a headless run
+pub fn start_queued_replay_now_system(mut queued: ResMut<QueuedReplay>, mut run: ReplayedRun) {
+    if let Some(replay) = queued.0.take() {
+        run.start(replay);
+    }
+}
+
+fn start_queued_replay_system(
+    mut queued: ResMut<QueuedReplay>,
+    mut state: ResMut<State<GameState>>,
+    mut run: ReplayedRun,
+) {
+    if let Some(replay) = queued.0.take() {
+        run.start(replay);
+        let _ = state.push(GameState::MainGame);
+    }
+}
+
+fn start_recording_system(
+    mut recorder: ResMut<ReplayRecorder>,
+    playback: Res<ReplayPlayback>,
+    game_rng: Res<GameRng>,
+    game_mode: Res<GameMode>,
+    settings: Res<Settings>,
+    win_size: Res<WindowSize>,
+) {
+    recorder.ticks = 0;
+    recorder.difficulty = settings.difficulty;
+    recorder.window = (win_size.w, win_size.h);
+    recorder.replay = if playback.is_playing() {
+        None
+    } else {
+        Some(Replay {
+            version: env!("CARGO_PKG_VERSION").to_string(),
+            seed: game_rng.seed(),
+            mode: *game_mode,
+            settings: settings.clone(),
+            window: (win_size.w, win_size.h),
+            inputs: Vec::new(),
+            events: Vec::new(),
+        })
+    };
+}
+
+fn record_system(
+    mut recorder: ResMut<ReplayRecorder>,
+    input: Res<PlayerInput>,
+    settings: Res<Settings>,
+    win_size: Res<WindowSize>,
+    mut restart_events: EventReader<RestartRoundEvent>,
+) {
+    let recorder = &mut *recorder;
+    let tick = recorder.ticks;
+    let replay = match recorder.replay.as_mut() {
+        Some(replay) => replay,
+        None => {
+            restart_events.clear();
+            return;
+        }
+    };
+    for event in restart_events.iter() {
+        replay
+            .events
+            .push((tick, ReplayEvent::RestartRound(event.score)));
+    }
+    if settings.difficulty != recorder.difficulty {
+        recorder.difficulty = settings.difficulty;
+        replay
+            .events
+            .push((tick, ReplayEvent::Difficulty(settings.difficulty)));
+    }
+    if (win_size.w, win_size.h) != recorder.window {
+        recorder.window = (win_size.w, win_size.h);
+        replay
+            .events
+            .push((tick, ReplayEvent::WindowSize(win_size.w, win_size.h)));
+    }
+    replay.push_input(*input);
+    recorder.ticks += 1;
+}
+
+//Runs after the controls, so whatever the viewer is pressing is replaced by what was recorded
+fn playback_system(
+    mut playback: ResMut<ReplayPlayback>,
+    mut input: ResMut<PlayerInput>,
+    mut settings: ResMut<Settings>,
+    mut win_size: ResMut<WindowSize>,
+    mut fixed_time: ResMut<FixedTime>,
+    mut send_restart: EventWriter<RestartRoundEvent>,
+) {
+    let playback = match playback.0.as_mut() {
+        Some(playback) => playback,
+        None => return,
+    };
+    let tick = playback.tick as u64;
+    for (_, event) in playback.events.iter().filter(|(at, _)| *at == tick) {
+        match event {
+            ReplayEvent::RestartRound(score) => {
+                send_restart.send(RestartRoundEvent { score: *score })
+            }
+            ReplayEvent::Difficulty(difficulty) => settings.difficulty = *difficulty,
+            ReplayEvent::WindowSize(w, h) => {
+                win_size.w = *w;
+                win_size.h = *h;
+            }
+        }
+    }
+    match playback.inputs.get(playback.tick) {
+        Some(recorded) => {
+            *input = *recorded;
+            playback.tick += 1;
+        }
+        //The recording stopped here, whether the player quit or closed the game
+        None => {
+            *input = PlayerInput::default();
+            if !playback.finished {
+                playback.finished = true;
+                fixed_time.set_paused(true);
+            }
+        }
+    }
+}
+
+fn replay_controls_system(
+    keys: Res<Input<KeyCode>>,
+    playback: Res<ReplayPlayback>,
+    mut fixed_time: ResMut<FixedTime>,
+) {
+    //Nothing left to control once it's finished
+    match playback.0.as_ref() {
+        Some(playback) if !playback.finished => {}
+        _ => return,
+    }
+    if keys.just_pressed(KeyCode::Space) {
+        let paused = !fixed_time.paused();
+        fixed_time.set_paused(paused);
+    }
+    if keys.just_pressed(KeyCode::Period) {
+        fixed_time.step_once();
+    }
+    if keys.just_pressed(KeyCode::F) {
+        let current = REPLAY_SPEEDS
+            .iter()
+            .position(|speed| *speed == fixed_time.speed())
+            .unwrap_or(0);
+        fixed_time.set_speed(REPLAY_SPEEDS[(current + 1) % REPLAY_SPEEDS.len()]);
+    }
+}
+
+fn save_recording_system(mut recorder: ResMut<ReplayRecorder>) {
+    recorder.save();
+}
+
+fn save_recording_on_exit_system(
+    mut app_exit: EventReader<AppExit>,
+    mut recorder: ResMut<ReplayRecorder>,
+) {
+    if app_exit.iter().next().is_some() {
+        recorder.save();
+    }
+}
+
+fn stop_playback_system(
+    mut playback: ResMut<ReplayPlayback>,
+    mut settings: ResMut<Settings>,
+    mut win_size: ResMut<WindowSize>,
+    mut fixed_time: ResMut<FixedTime>,
+) {
+    let playback = match playback.0.take() {
+        Some(playback) => playback,
+        None => return,
+    };
+    if settings.difficulty != playback.player_difficulty {
+        settings.difficulty = playback.player_difficulty;
+    }
+    win_size.w = playback.player_window.0;
+    win_size.h = playback.player_window.1;
+    fixed_time.set_speed(1.0);
+    fixed_time.set_paused(false);
+}
+
+#[derive(Component)]
+struct ReplayText;
+
+fn spawn_replay_ui_system(
+    mut commands: Commands,
+    font: Res<GameFont>,
+    playback: Res<ReplayPlayback>,
+) {
+    if !playback.is_playing() {
+        return;
+    }
+    commands
+        .spawn_bundle(
+            TextBundle::from_section(
+                "",
+                TextStyle {
+                    font: font.0.clone(),
+                    font_size: 24.0,
+                    color: Color::rgb(0.9, 0.9, 0.9),
+                },
+            )
+            .with_style(Style {
+                position_type: PositionType::Absolute,
+                position: UiRect {
+                    left: Val::Percent(35.0),
+                    top: Val::Percent(1.0),
+                    ..default()
+                },
+                ..default()
+            }),
+        )
+        .insert(ReplayText)
+        .insert(HudText { base_size: 24.0 })
+        .insert(GameUi);
+}
+
+fn clock(ticks: usize) -> String {
+    let seconds = (ticks as f32 * TIME_STEP) as u32;
+    format!("{}:{:02}", seconds / 60, seconds % 60)
+}
+
+fn update_replay_ui_system(
+    playback: Res<ReplayPlayback>,
+    fixed_time: Res<FixedTime>,
+    mut query: Query<&mut Text, With<ReplayText>>,
+) {
+    let playback = match playback.0.as_ref() {
+        Some(playback) => playback,
+        None => return,
+    };
+    let status = if playback.finished {
+        "Finished, Esc to leave".to_string()
+    } else if fixed_time.paused() {
+        "Paused, Space to play, . to step".to_string()
+    } else {
+        format!("x{}, F to speed up, Space to pause", fixed_time.speed())
+    };
+    for mut text in query.iter_mut() {
+        text.sections[0].value = format!(
+            "Replay {} / {}  {}",
+            clock(playback.tick),
+            clock(playback.inputs.len()),
+            status
+        );
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn empty_replay() -> Replay {
+        Replay {
+            version: env!("CARGO_PKG_VERSION").to_string(),
+            seed: 42,
+            mode: GameMode::Classic,
+            settings: Settings::default(),
+            window: (1024., 768.),
+            inputs: Vec::new(),
+            events: Vec::new(),
+        }
+    }
+
+    fn input(x: f32, firing: bool) -> PlayerInput {
+        PlayerInput {
+            movement: Vec2::new(x, 0.),
+            aim: Vec2::new(0.5, -1.),
+            firing,
+            ..Default::default()
+        }
+    }
+
+    #[test]
+    fn every_button_survives_the_tick_encoding() {
+        for buttons in 0..8u8 {
+            let input = PlayerInput {
+                movement: Vec2::new(-1., 0.25),
+                aim: Vec2::new(3., 4.),
+                firing: buttons & 1 != 0,
+                jump: buttons & 2 != 0,
+                crouch: buttons & 4 != 0,
+            };
+            assert_eq!(PlayerInput::from(TickInput::from(input)), input);
+        }
+    }
+
+    #[test]
+    fn repeated_input_is_stored_as_runs() {
+        let mut replay = empty_replay();
+        let ticks = [
+            input(1., true),
+            input(1., true),
+            input(1., true),
+            input(0., false),
+            input(1., true),
+        ];
+        for tick in ticks {
+            replay.push_input(tick);
+        }
+        let runs: Vec<u32> = replay.inputs.iter().map(|(run, _)| *run).collect();
+        assert_eq!(runs, [3, 1, 1]);
+        assert_eq!(replay.tick_inputs(), ticks);
+    }
+
+    #[test]
+    fn saved_replay_loads_back() {
+        let dir = std::env::temp_dir().join("mbrh_replay");
+        let _ = fs::remove_dir_all(&dir);
+        let path = dir.join("run.replay");
+
+        let mut replay = empty_replay();
+        for tick in 0..100 {
+            replay.push_input(input((tick / 10) as f32, tick % 3 == 0));
+        }
+        replay
+            .events
+            .push((20, ReplayEvent::Difficulty(Difficulty::Hard)));
+        replay
+            .events
+            .push((50, ReplayEvent::WindowSize(1600., 900.)));
+        replay.save(&path).unwrap();
+        assert!(!path.with_extension("replay.tmp").exists());
+
+        let loaded = Replay::load(&path).unwrap();
+        assert_eq!(loaded.seed, 42);
+        assert_eq!(loaded.window, (1024., 768.));
+        assert_eq!(loaded.tick_inputs(), replay.tick_inputs());
+        assert!(matches!(
+            loaded.events[..],
+            [
+                (20, ReplayEvent::Difficulty(Difficulty::Hard)),
+                (50, ReplayEvent::WindowSize(w, h))
+            ] if (w, h) == (1600., 900.)
+        ));
+    }
+}
@@ -1,8 +1,12 @@
 use bevy::prelude::*;
+use rand::rngs::StdRng;
+use rand::{RngCore, SeedableRng};
+use serde::{Deserialize, Serialize};
 use std::collections::VecDeque;
 
 use crate::utils::CooldownTimer;
 
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Hash)]
 pub enum SpawnType {
     Civilian,
     Crab,
@@ -11,6 +15,28 @@ pub enum SpawnType {
     Exploder,
 }
 
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Hash, Serialize, Deserialize)]
+pub enum GameMode {
+    Classic,
+    Endless,
+}
+
+impl GameMode {
+    pub fn name(&self) -> &'static str {
+        match self {
+            GameMode::Classic => "Classic",
+            GameMode::Endless => "Endless",
+        }
+    }
+
+    pub fn next(&self) -> GameMode {
+        match self {
+            GameMode::Classic => GameMode::Endless,
+            GameMode::Endless => GameMode::Classic,
+        }
+    }
+}
+
 pub struct WindowSize {
     pub w: f32,
     pub h: f32,
@@ -19,6 +45,7 @@ pub struct WindowSize {
 pub struct GameTextures {
     pub player: Handle<Image>,
     pub enemy: Handle<Image>,
+    pub shadow: Handle<Image>,
 }
 
 pub struct GameFont(pub Handle<Font>);
@@ -39,3 +66,42 @@ pub struct BlasterHeat {
 
 #[derive(Deref, DerefMut)]
 pub struct SpawnQueue(pub VecDeque<SpawnType>);
+
+//Everything random in gameplay comes from here, seeded once per run, so a run plays out the same again
+//from its seed and inputs. Purely cosmetic randomness (particles, sounds, quips) sticks to thread_rng so
+//it can't shift what gameplay draws next
+pub struct GameRng {
+    seed: u64,
+    rng: StdRng,
+}
+
+impl GameRng {
+    pub fn new(seed: u64) -> Self {
+        Self {
+            seed,
+            rng: StdRng::seed_from_u64(seed),
+        }
+    }
+
+    pub fn seed(&self) -> u64 {
+        self.seed
+    }
+}
+
+impl RngCore for GameRng {
+    fn next_u32(&mut self) -> u32 {
+        self.rng.next_u32()
+    }
+
+    fn next_u64(&mut self) -> u64 {
+        self.rng.next_u64()
+    }
+
+    fn fill_bytes(&mut self, dest: &mut [u8]) {
+        self.rng.fill_bytes(dest)
+    }
+
+    fn try_fill_bytes(&mut self, dest: &mut [u8]) -> Result<(), rand::Error> {
+        self.rng.try_fill_bytes(dest)
+    }
+}
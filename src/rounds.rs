@@ -1,14 +1,21 @@
 use bevy::prelude::*;
+use rand::rngs::StdRng;
+use rand::{Rng, SeedableRng};
 use serde::Deserialize;
 use std::collections::VecDeque;
 use std::path::PathBuf;
+use std::time::{SystemTime, UNIX_EPOCH};
 
-use crate::components::{Civilian, Enemy};
-use crate::resources::{SpawnQueue, SpawnType};
+use crate::components::{Blaster, Civilian, Enemy};
+use crate::constants::*;
+use crate::debug;
+use crate::fixed_step::{FixedStepApp, FixedSystems};
+use crate::pause::RestartRoundEvent;
+use crate::resources::{GameMode, GameRng, SpawnQueue, SpawnType};
 use crate::spawn_manager::NewRoundEvent;
 use crate::states::GameState;
 
-#[derive(Copy, Clone, Debug, Deserialize)]
+#[derive(Copy, Clone, Debug, Default, PartialEq, Eq, Deserialize)]
 pub struct RoundSpawns {
     pub max_spawns: u32,
     pub number_of_civilians: u32,
@@ -18,8 +25,6 @@ pub struct RoundSpawns {
     pub number_of_exploders: u32,
 }
 
-pub struct PopulateQueueEvent {}
-
 fn parse_round_spawns(
     round_spawn_path: PathBuf,
 ) -> Result<Vec<RoundSpawns>, Box<dyn std::error::Error>> {
@@ -32,9 +37,88 @@ fn parse_round_spawns(
     Ok(round_data)
 }
 
+//What each archetype takes out of a round's budget, the tougher they are the fewer fit in
+const ENDLESS_ARCHETYPES: [(SpawnType, u32); 4] = [
+    (SpawnType::Crab, CRAB_COST),
+    (SpawnType::Bot, BOT_COST),
+    (SpawnType::Exploder, EXPLODER_COST),
+    (SpawnType::Tank, TANK_COST),
+];
+
+//Builds rounds from a difficulty budget that grows every round. Each round gets its own rng derived
+//from the seed so a given round always comes out the same no matter how the earlier ones were played
+pub struct EndlessGenerator {
+    pub seed: u64,
+}
+
+impl EndlessGenerator {
+    pub fn new(seed: u64) -> Self {
+        Self { seed }
+    }
+
+    pub fn budget(&self, round: u32) -> u32 {
+        ENDLESS_STARTING_BUDGET + round * ENDLESS_BUDGET_GROWTH
+    }
+
+    pub fn generate(&self, round: u32) -> RoundSpawns {
+        let mut rng =
+            StdRng::seed_from_u64(self.seed ^ (round as u64).wrapping_mul(0x9E37_79B9_7F4A_7C15));
+        let mut spawns = RoundSpawns {
+            max_spawns: (ENDLESS_STARTING_MAX_SPAWNS + round / 2).min(ENDLESS_MAX_SPAWNS_CAP),
+            number_of_civilians: 1 + round / 3,
+            ..Default::default()
+        };
+
+        let mut budget = self.budget(round);
+        loop {
+            let affordable: Vec<(SpawnType, u32)> = ENDLESS_ARCHETYPES
+                .iter()
+                .copied()
+                .filter(|(_, cost)| *cost <= budget)
+                .collect();
+            if affordable.is_empty() {
+                break;
+            }
+
+            let (archetype, cost) = affordable[rng.gen_range(0..affordable.len())];
+            budget -= cost;
+            match archetype {
+                SpawnType::Crab => spawns.number_of_crabs += 1,
+                SpawnType::Bot => spawns.number_of_bots += 1,
+                SpawnType::Tank => spawns.number_of_tanks += 1,
+                SpawnType::Exploder => spawns.number_of_exploders += 1,
+                SpawnType::Civilian => spawns.number_of_civilians += 1,
+            }
+        }
+        spawns
+    }
+}
+
+//Each run gets a new seed unless MBRH_SEED pins one, the seed of every run is logged when it starts
+pub fn new_run_seed() -> u64 {
+    debug::seed_override().unwrap_or_else(|| {
+        SystemTime::now()
+            .duration_since(UNIX_EPOCH)
+            .map(|time| time.as_nanos() as u64)
+            .unwrap_or_default()
+    })
+}
+
+//Everything about a run that comes from its seed, the endless rounds and the gameplay rng
+pub fn seed_run(
+    seed: u64,
+    mode: GameMode,
+    round_tracker: &mut RoundTracker,
+    game_rng: &mut GameRng,
+) {
+    *round_tracker = RoundTracker::for_mode(mode, seed);
+    *game_rng = GameRng::new(seed);
+}
+
 pub struct RoundTracker {
     pub current_round: Option<u32>,
     round_data: Vec<RoundSpawns>,
+    endless: Option<EndlessGenerator>,
 }
 
 impl RoundTracker {
@@ -42,15 +126,37 @@ impl RoundTracker {
         let mut tracker = RoundTracker {
             current_round: None,
             round_data: parse_round_spawns(PathBuf::from("assets/round_data.txt")).unwrap(),
+            endless: None,
         };
         tracker.current_round = Some(0);
         tracker
     }
 
+    pub fn endless(generator: EndlessGenerator) -> Self {
+        RoundTracker {
+            current_round: Some(0),
+            round_data: vec![generator.generate(0)],
+            endless: Some(generator),
+        }
+    }
+
+    pub fn for_mode(mode: GameMode, seed: u64) -> Self {
+        match mode {
+            GameMode::Classic => Self::start(),
+            GameMode::Endless => Self::endless(EndlessGenerator::new(seed)),
+        }
+    }
+
     pub fn next_round(&mut self) -> bool {
         if let Some(mut current_round) = self.current_round {
             current_round += 1;
             self.current_round = Some(current_round);
+            if let Some(generator) = &self.endless {
+                while self.round_data.len() <= current_round as usize {
+                    let round = self.round_data.len() as u32;
+                    self.round_data.push(generator.generate(round));
+                }
+            }
             if current_round as usize >= self.number_of_rounds() {
                 return false;
             }
@@ -58,6 +164,19 @@ impl RoundTracker {
         true
     }
 
+    pub fn is_final_round(&self) -> bool {
+        match self.current_round {
+            Some(current_round) => {
+                !self.is_endless() && current_round as usize + 1 >= self.number_of_rounds()
+            }
+            None => false,
+        }
+    }
+
+    pub fn is_endless(&self) -> bool {
+        self.endless.is_some()
+    }
+
     pub fn number_of_rounds(&self) -> usize {
         self.round_data.len()
     }
@@ -75,45 +194,123 @@ pub struct RoundManagerPlugin;
 
 impl Plugin for RoundManagerPlugin {
     fn build(&self, app: &mut App) {
-        app.add_event::<PopulateQueueEvent>()
-            .add_event::<NewRoundEvent>()
+        app.add_fixed_event::<NewRoundEvent>()
+            .add_fixed_event::<RestartRoundEvent>()
             .add_startup_system(insert_startup_resources)
             .add_system_set(
                 SystemSet::on_enter(GameState::MainGame).with_system(start_round_system),
             )
-            .add_system_set(
-                SystemSet::on_update(GameState::MainGame).with_system(populate_spawn_queue_system),
+            .add_fixed_systems(
+                FixedSystems::on_update(GameState::MainGame).with_system(restart_round_system),
             );
     }
 }
 
 fn insert_startup_resources(mut cmds: Commands) {
     cmds.insert_resource(SpawnQueue(VecDeque::new()));
+    cmds.insert_resource(GameMode::Classic);
     cmds.insert_resource(RoundTracker::start());
+    //Reseeded as each run starts
+    cmds.insert_resource(GameRng::new(0));
 }
 
 fn start_round_system(
-    mut cmds: Commands,
-    mut send_populate_queue: EventWriter<PopulateQueueEvent>,
+    mut spawn_queue: ResMut<SpawnQueue>,
+    round_tracker: Res<RoundTracker>,
+    mut send_new_round: EventWriter<NewRoundEvent>,
+) {
+    start_round(&mut spawn_queue, &round_tracker, &mut send_new_round);
+}
+
+//The queue is filled straight away rather than through an event, otherwise the spawn manager can see an
+//empty queue and an empty field on the same frame and think the round is already over
+pub fn start_round(
+    spawn_queue: &mut SpawnQueue,
+    round_tracker: &RoundTracker,
+    send_new_round: &mut EventWriter<NewRoundEvent>,
 ) {
-    send_populate_queue.send(PopulateQueueEvent {});
+    fill_spawn_queue(spawn_queue, round_tracker);
+    send_new_round.send(NewRoundEvent {
+        round: round_tracker.current_round.unwrap(),
+    });
+}
+
+fn fill_spawn_queue(spawn_queue: &mut SpawnQueue, round_tracker: &RoundTracker) {
+    let round_data = round_tracker.current_round_data().unwrap();
+    spawn_queue.clear();
+    for _ in 0..round_data.number_of_civilians {
+        spawn_queue.push_back(SpawnType::Civilian);
+    }
+    for (archetype, count) in [
+        (SpawnType::Crab, round_data.number_of_crabs),
+        (SpawnType::Bot, round_data.number_of_bots),
+        (SpawnType::Exploder, round_data.number_of_exploders),
+        (SpawnType::Tank, round_data.number_of_tanks),
+    ] {
+        for _ in 0..count {
+            spawn_queue.push_back(archetype);
+        }
+    }
 }
 
-fn populate_spawn_queue_system(
-    mut populate_queue_events: EventReader<PopulateQueueEvent>,
+type RoundEntities = Or<(With<Enemy>, With<Civilian>, With<Blaster>)>;
+
+//Clears the field and queues the same round up again, no NewRoundEvent since the round number hasn't changed
+fn restart_round_system(
+    mut cmds: Commands,
+    mut restart_events: EventReader<RestartRoundEvent>,
     mut spawn_queue: ResMut<SpawnQueue>,
-    mut round_tracker: ResMut<RoundTracker>,
+    round_tracker: Res<RoundTracker>,
+    query: Query<Entity, RoundEntities>,
 ) {
-    if populate_queue_events.len() > 0 {
-        if (spawn_queue.len() == 0) {
-            let round_data = round_tracker.current_round_data().unwrap();
-            for _ in 0..round_data.number_of_civilians {
-                spawn_queue.push_back(SpawnType::Civilian);
-            }
-            for _ in 0..round_data.number_of_crabs {
-                spawn_queue.push_back(SpawnType::Crab);
-            }
+    if restart_events.iter().last().is_none() {
+        return;
+    }
+
+    for entity in query.iter() {
+        cmds.entity(entity).despawn_recursive();
+    }
+    fill_spawn_queue(&mut spawn_queue, &round_tracker);
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn spent(spawns: &RoundSpawns) -> u32 {
+        spawns.number_of_crabs * CRAB_COST
+            + spawns.number_of_bots * BOT_COST
+            + spawns.number_of_exploders * EXPLODER_COST
+            + spawns.number_of_tanks * TANK_COST
+    }
+
+    #[test]
+    fn same_seed_makes_the_same_waves() {
+        let first = EndlessGenerator::new(42);
+        let second = EndlessGenerator::new(42);
+        for round in 0..20 {
+            assert_eq!(first.generate(round), second.generate(round));
         }
-        populate_queue_events.clear();
+    }
+
+    #[test]
+    fn waves_stay_within_their_budget() {
+        let generator = EndlessGenerator::new(7);
+        for round in 0..20 {
+            let spawns = generator.generate(round);
+            assert!(spent(&spawns) <= generator.budget(round));
+        }
+    }
+
+    #[test]
+    fn later_waves_mix_archetypes() {
+        let generator = EndlessGenerator::new(7);
+        let spawns = (0..20).map(|round| generator.generate(round));
+        let tough = spawns
+            .map(|spawns| {
+                spawns.number_of_bots + spawns.number_of_exploders + spawns.number_of_tanks
+            })
+            .sum::<u32>();
+        assert!(tough > 0);
     }
 }
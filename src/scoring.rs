@@ -0,0 +1,164 @@
+use bevy::prelude::*;
+
+use crate::civilian::CivilianRescuedEvent;
+use crate::components::Player;
+use crate::constants::*;
+use crate::fixed_step::{FixedStepApp, FixedSystems, FixedTime};
+use crate::pause::RestartRoundEvent;
+use crate::projectile_collision::{EnemyKilledEvent, LivingBeingDeathEvent, LivingBeingHitEvent};
+use crate::resources::{PlayerScore, SpawnType};
+use crate::states::GameState;
+use crate::utils::CooldownTimer;
+
+pub struct ScoringPlugin;
+
+impl Plugin for ScoringPlugin {
+    fn build(&self, app: &mut App) {
+        app.add_fixed_event::<ScoreEvent>()
+            .insert_resource(Combo::default())
+            .add_fixed_systems(
+                FixedSystems::on_update(GameState::MainGame)
+                    .with_system(award_kill_points)
+                    .with_system(award_rescue_points)
+                    .with_system(combo_timeout_system)
+                    .with_system(reset_combo_on_hit)
+                    .with_system(apply_score_events)
+                    .with_system(restart_round_system.after(apply_score_events)),
+            );
+    }
+}
+
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum ScoreReason {
+    Kill(SpawnType),
+    PowerShotBonus,
+    CivilianRescued,
+}
+
+//Every point the player earns goes through one of these. The only other writes to PlayerScore are the
+//resets, when a run starts and when a round is restarted
+pub struct ScoreEvent {
+    pub points: usize,
+    pub position: Vec2,
+    pub reason: ScoreReason,
+}
+
+pub struct Combo {
+    pub multiplier: u32,
+    pub chain: u32,
+    pub window: CooldownTimer,
+}
+
+impl Default for Combo {
+    fn default() -> Self {
+        Self {
+            multiplier: 1,
+            chain: 0,
+            window: CooldownTimer::from_seconds(COMBO_WINDOW_SECONDS),
+        }
+    }
+}
+
+impl Combo {
+    fn register_kill(&mut self) {
+        self.chain += 1;
+        self.multiplier = (1 + self.chain / COMBO_KILLS_PER_STEP).min(MAX_COMBO_MULTIPLIER);
+        self.window.trigger();
+    }
+
+    fn reset(&mut self) {
+        *self = Combo::default();
+    }
+}
+
+pub fn kill_points(archetype: SpawnType) -> usize {
+    match archetype {
+        SpawnType::Crab => CRAB_POINTS,
+        SpawnType::Bot => BOT_POINTS,
+        SpawnType::Tank => TANK_POINTS,
+        SpawnType::Exploder => EXPLODER_POINTS,
+        SpawnType::Civilian => 0,
+    }
+}
+
+fn award_kill_points(
+    mut kill_events: EventReader<EnemyKilledEvent>,
+    mut combo: ResMut<Combo>,
+    mut send_score: EventWriter<ScoreEvent>,
+) {
+    for event in kill_events.iter() {
+        combo.register_kill();
+        let multiplier = combo.multiplier as usize;
+
+        send_score.send(ScoreEvent {
+            points: kill_points(event.archetype) * multiplier,
+            position: event.position,
+            reason: ScoreReason::Kill(event.archetype),
+        });
+        if event.power_shot {
+            send_score.send(ScoreEvent {
+                points: POWER_SHOT_KILL_BONUS * multiplier,
+                position: event.position,
+                reason: ScoreReason::PowerShotBonus,
+            });
+        }
+    }
+}
+
+fn award_rescue_points(
+    mut rescue_events: EventReader<CivilianRescuedEvent>,
+    combo: Res<Combo>,
+    mut send_score: EventWriter<ScoreEvent>,
+) {
+    for event in rescue_events.iter() {
+        send_score.send(ScoreEvent {
+            points: CIVILIAN_RESCUE_POINTS * combo.multiplier as usize,
+            position: event.position,
+            reason: ScoreReason::CivilianRescued,
+        });
+    }
+}
+
+fn combo_timeout_system(time: Res<FixedTime>, mut combo: ResMut<Combo>) {
+    if combo.chain == 0 {
+        return;
+    }
+    combo.window.tick(time.delta());
+    if combo.window.ready() {
+        combo.reset();
+    }
+}
+
+fn reset_combo_on_hit(
+    mut hit_events: EventReader<LivingBeingHitEvent>,
+    mut enemy_collision_events: EventReader<LivingBeingDeathEvent>,
+    player_query: Query<Entity, With<Player>>,
+    mut combo: ResMut<Combo>,
+) {
+    let player_hit = hit_events
+        .iter()
+        .map(|event| event.entity)
+        .chain(enemy_collision_events.iter().map(|event| event.entity))
+        .any(|entity| player_query.contains(entity));
+    if player_hit {
+        combo.reset();
+    }
+}
+
+fn apply_score_events(mut score_events: EventReader<ScoreEvent>, mut score: ResMut<PlayerScore>) {
+    for event in score_events.iter() {
+        score.0 += event.points;
+    }
+}
+
+//Restarting throws away whatever was scored during the attempt
+fn restart_round_system(
+    mut restart_events: EventReader<RestartRoundEvent>,
+    mut score: ResMut<PlayerScore>,
+    mut combo: ResMut<Combo>,
+) {
+    if let Some(event) = restart_events.iter().last() {
+        score.0 = event.score;
+        combo.reset();
+    }
+}
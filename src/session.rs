@@ -0,0 +1,81 @@
+use bevy::prelude::*;
+
+use crate::components::{Background, GameUi, LivingBeing, ScoreUi};
+use crate::constants::COOLDOWN_TIME_SECONDS;
+use crate::high_scores::PendingHighScore;
+use crate::resources::{BlasterHeat, GameMode, GameRng, PlayerLives, PlayerScore, SpawnQueue};
+use crate::rounds::{new_run_seed, seed_run, RoundTracker};
+use crate::scoring::Combo;
+use crate::states::GameState;
+use crate::stats::{RoundStats, RunStats};
+use crate::utils::CooldownTimer;
+
+//Tears a run down when MainGame is left for good so the next one starts from scratch. Everything a run
+//spawns is tagged LivingBeing, Background or GameUi, menus and the camera are left alone
+pub struct SessionPlugin;
+
+impl Plugin for SessionPlugin {
+    fn build(&self, app: &mut App) {
+        app.add_system_set(
+            SystemSet::on_exit(GameState::MainGame)
+                .with_system(despawn_session_entities)
+                .with_system(reset_player_resources)
+                .with_system(reset_round_resources),
+        );
+    }
+}
+
+type SessionEntities = Or<(With<LivingBeing>, With<Background>, With<GameUi>)>;
+
+//The score stays up on the game over screen
+type PlayFieldEntities = Or<(
+    With<LivingBeing>,
+    With<Background>,
+    (With<GameUi>, Without<ScoreUi>),
+)>;
+
+fn despawn_session_entities(mut commands: Commands, query: Query<Entity, SessionEntities>) {
+    for entity in query.iter() {
+        commands.entity(entity).despawn_recursive();
+    }
+}
+
+pub fn despawn_play_field(mut commands: Commands, query: Query<Entity, PlayFieldEntities>) {
+    for entity in query.iter() {
+        commands.entity(entity).despawn_recursive();
+    }
+}
+
+fn reset_player_resources(
+    mut score: ResMut<PlayerScore>,
+    mut lives: ResMut<PlayerLives>,
+    mut blaster_heat: ResMut<BlasterHeat>,
+    mut combo: ResMut<Combo>,
+) {
+    score.0 = 0;
+    lives.0 = 0;
+    blaster_heat.value = 0.;
+    blaster_heat.overheat_cooldown_timer = CooldownTimer::from_seconds(COOLDOWN_TIME_SECONDS);
+    *combo = Combo::default();
+}
+
+fn reset_round_resources(
+    mut spawn_queue: ResMut<SpawnQueue>,
+    mut round_stats: ResMut<RoundStats>,
+    mut run_stats: ResMut<RunStats>,
+    mut round_tracker: ResMut<RoundTracker>,
+    mut pending_high_score: ResMut<PendingHighScore>,
+    mut game_rng: ResMut<GameRng>,
+    game_mode: Res<GameMode>,
+) {
+    spawn_queue.clear();
+    *round_stats = RoundStats::default();
+    *run_stats = RunStats::default();
+    seed_run(
+        new_run_seed(),
+        *game_mode,
+        &mut round_tracker,
+        &mut game_rng,
+    );
+    pending_high_score.0 = None;
+}
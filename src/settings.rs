@@ -0,0 +1,579 @@
+use std::error::Error;
+use std::fs;
+use std::path::{Path, PathBuf};
+
+use bevy::prelude::*;
+use bevy::window::{WindowMode, WindowResized};
+use serde::{Deserialize, Serialize};
+
+use crate::constants::*;
+use crate::menu::{MenuActionPlugin, MenuBuilder, MenuLabel, MenuSelected, UIAssets};
+use crate::replay::ReplayPlayback;
+use crate::resources::WindowSize;
+use crate::states::GameState;
+
+pub struct SettingsPlugin;
+
+impl Plugin for SettingsPlugin {
+    fn build(&self, app: &mut App) {
+        app.add_plugin(MenuActionPlugin::<SettingsButton>::default())
+            .insert_resource(Rebinding(None))
+            .add_system(track_window_size)
+            .add_system_set(
+                SystemSet::on_enter(GameState::ControlMenu).with_system(setup_settings_menu),
+            )
+            .add_system_set(
+                SystemSet::on_update(GameState::ControlMenu)
+                    .with_system(handle_settings_buttons)
+                    .with_system(settings_keyboard_input)
+                    .with_system(update_setting_text)
+                    .with_system(apply_window_settings),
+            )
+            .add_system_set(
+                SystemSet::on_exit(GameState::ControlMenu)
+                    .with_system(despawn_settings_menu)
+                    .with_system(save_settings),
+            );
+    }
+}
+
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Serialize, Deserialize)]
+pub enum WindowModeSetting {
+    Windowed,
+    Borderless,
+    Fullscreen,
+}
+
+impl WindowModeSetting {
+    fn name(&self) -> &'static str {
+        match self {
+            WindowModeSetting::Windowed => "Windowed",
+            WindowModeSetting::Borderless => "Borderless",
+            WindowModeSetting::Fullscreen => "Fullscreen",
+        }
+    }
+
+    fn next(&self) -> WindowModeSetting {
+        match self {
+            WindowModeSetting::Windowed => WindowModeSetting::Borderless,
+            WindowModeSetting::Borderless => WindowModeSetting::Fullscreen,
+            WindowModeSetting::Fullscreen => WindowModeSetting::Windowed,
+        }
+    }
+
+    fn window_mode(&self) -> WindowMode {
+        match self {
+            WindowModeSetting::Windowed => WindowMode::Windowed,
+            WindowModeSetting::Borderless => WindowMode::BorderlessFullscreen,
+            WindowModeSetting::Fullscreen => WindowMode::Fullscreen,
+        }
+    }
+}
+
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Serialize, Deserialize)]
+pub enum Difficulty {
+    Easy,
+    Normal,
+    Hard,
+}
+
+impl Difficulty {
+    fn name(&self) -> &'static str {
+        match self {
+            Difficulty::Easy => "Easy",
+            Difficulty::Normal => "Normal",
+            Difficulty::Hard => "Hard",
+        }
+    }
+
+    fn next(&self) -> Difficulty {
+        match self {
+            Difficulty::Easy => Difficulty::Normal,
+            Difficulty::Normal => Difficulty::Hard,
+            Difficulty::Hard => Difficulty::Easy,
+        }
+    }
+
+    pub fn enemy_speed_multiplier(&self) -> f32 {
+        match self {
+            Difficulty::Easy => 0.75,
+            Difficulty::Normal => 1.0,
+            Difficulty::Hard => 1.3,
+        }
+    }
+}
+
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum Binding {
+    Up,
+    Down,
+    Left,
+    Right,
+    Jump,
+    Crouch,
+}
+
+impl Binding {
+    fn name(&self) -> &'static str {
+        match self {
+            Binding::Up => "Up",
+            Binding::Down => "Down",
+            Binding::Left => "Left",
+            Binding::Right => "Right",
+            Binding::Jump => "Jump",
+            Binding::Crouch => "Crouch",
+        }
+    }
+}
+
+#[derive(Clone, Debug, Serialize, Deserialize)]
+pub struct KeyBindings {
+    pub up: KeyCode,
+    pub down: KeyCode,
+    pub left: KeyCode,
+    pub right: KeyCode,
+    pub jump: KeyCode,
+    pub crouch: KeyCode,
+}
+
+impl Default for KeyBindings {
+    fn default() -> Self {
+        Self {
+            up: KeyCode::W,
+            down: KeyCode::S,
+            left: KeyCode::A,
+            right: KeyCode::D,
+            jump: KeyCode::Space,
+            crouch: KeyCode::C,
+        }
+    }
+}
+
+impl KeyBindings {
+    pub fn get(&self, binding: Binding) -> KeyCode {
+        match binding {
+            Binding::Up => self.up,
+            Binding::Down => self.down,
+            Binding::Left => self.left,
+            Binding::Right => self.right,
+            Binding::Jump => self.jump,
+            Binding::Crouch => self.crouch,
+        }
+    }
+
+    fn set(&mut self, binding: Binding, key: KeyCode) {
+        let slot = match binding {
+            Binding::Up => &mut self.up,
+            Binding::Down => &mut self.down,
+            Binding::Left => &mut self.left,
+            Binding::Right => &mut self.right,
+            Binding::Jump => &mut self.jump,
+            Binding::Crouch => &mut self.crouch,
+        };
+        *slot = key;
+    }
+}
+
+//Missing fields fall back to their defaults so older config files keep loading as settings get added
+#[derive(Clone, Debug, Serialize, Deserialize)]
+#[serde(default)]
+pub struct Settings {
+    #[serde(skip)]
+    path: PathBuf,
+    pub master_volume: f32,
+    pub music_volume: f32,
+    pub sfx_volume: f32,
+    pub window_mode: WindowModeSetting,
+    pub resolution: (f32, f32),
+    pub bindings: KeyBindings,
+    //How far the right stick has to move before it starts aiming
+    pub stick_deadzone: f32,
+    pub difficulty: Difficulty,
+}
+
+impl Default for Settings {
+    fn default() -> Self {
+        Self {
+            path: PathBuf::new(),
+            master_volume: 1.0,
+            music_volume: 0.8,
+            sfx_volume: 0.8,
+            window_mode: WindowModeSetting::Windowed,
+            resolution: RESOLUTIONS[0],
+            bindings: KeyBindings::default(),
+            stick_deadzone: DEFAULT_STICK_DEADZONE,
+            difficulty: Difficulty::Normal,
+        }
+    }
+}
+
+impl Settings {
+    pub fn default_path() -> PathBuf {
+        dirs::config_dir()
+            .unwrap_or_else(|| PathBuf::from("."))
+            .join("my-blaster-runs-hot")
+            .join("settings.ron")
+    }
+
+    //Like the high score table a broken file shouldn't stop the game from starting
+    pub fn load(path: PathBuf) -> Self {
+        let mut settings = if path.exists() {
+            match read_settings(&path) {
+                Ok(settings) => settings,
+                Err(err) => {
+                    eprintln!(
+                        "Settings file {} couldn't be read ({}), using the defaults",
+                        path.display(),
+                        err
+                    );
+                    Settings::default()
+                }
+            }
+        } else {
+            Settings::default()
+        };
+        settings.path = path;
+        settings
+    }
+
+    pub fn save(&self) -> Result<(), Box<dyn Error>> {
+        if let Some(parent) = self.path.parent() {
+            fs::create_dir_all(parent)?;
+        }
+        let contents = ron::ser::to_string_pretty(self, ron::ser::PrettyConfig::default())?;
+        let temp_path = self.path.with_extension("ron.tmp");
+        fs::write(&temp_path, contents)?;
+        fs::rename(&temp_path, &self.path)?;
+        Ok(())
+    }
+
+    pub fn window_descriptor(&self) -> WindowDescriptor {
+        WindowDescriptor {
+            title: "My Blaster Runs Hot".to_string(),
+            width: self.resolution.0,
+            height: self.resolution.1,
+            mode: self.window_mode.window_mode(),
+            ..Default::default()
+        }
+    }
+
+    pub fn sfx_volume(&self) -> f32 {
+        self.master_volume * self.sfx_volume
+    }
+
+    pub fn music_volume(&self) -> f32 {
+        self.master_volume * self.music_volume
+    }
+}
+
+fn read_settings(path: &Path) -> Result<Settings, Box<dyn Error>> {
+    let contents = fs::read_to_string(path)?;
+    Ok(ron::from_str(&contents)?)
+}
+
+fn next_volume(volume: f32) -> f32 {
+    if volume >= 1.0 - VOLUME_STEP / 2. {
+        0.
+    } else {
+        (volume + VOLUME_STEP).min(1.0)
+    }
+}
+
+fn next_resolution(resolution: (f32, f32)) -> (f32, f32) {
+    let current = RESOLUTIONS.iter().position(|res| *res == resolution);
+    match current {
+        Some(index) => RESOLUTIONS[(index + 1) % RESOLUTIONS.len()],
+        None => RESOLUTIONS[0],
+    }
+}
+
+fn next_deadzone(deadzone: f32) -> f32 {
+    if deadzone >= MAX_STICK_DEADZONE - STICK_DEADZONE_STEP / 2. {
+        MIN_STICK_DEADZONE
+    } else {
+        deadzone + STICK_DEADZONE_STEP
+    }
+}
+
+//Which key binding is waiting for the next key press, if any
+struct Rebinding(Option<Binding>);
+
+#[derive(Component)]
+struct SettingsMenu;
+
+#[derive(Component, Clone, Copy, PartialEq, Eq)]
+enum SettingsButton {
+    MasterVolume,
+    MusicVolume,
+    SfxVolume,
+    WindowMode,
+    Resolution,
+    Bind(Binding),
+    StickDeadzone,
+    Difficulty,
+    Back,
+}
+
+impl SettingsButton {
+    fn label(&self, settings: &Settings, rebinding: &Rebinding) -> String {
+        match self {
+            SettingsButton::MasterVolume => {
+                format!("Master Volume: {:.0}%", settings.master_volume * 100.)
+            }
+            SettingsButton::MusicVolume => {
+                format!("Music Volume: {:.0}%", settings.music_volume * 100.)
+            }
+            SettingsButton::SfxVolume => format!("SFX Volume: {:.0}%", settings.sfx_volume * 100.),
+            SettingsButton::WindowMode => format!("Window: {}", settings.window_mode.name()),
+            SettingsButton::Resolution => format!(
+                "Resolution: {}x{}",
+                settings.resolution.0, settings.resolution.1
+            ),
+            SettingsButton::Bind(binding) => {
+                if rebinding.0 == Some(*binding) {
+                    format!("{}: press a key", binding.name())
+                } else {
+                    format!("{}: {:?}", binding.name(), settings.bindings.get(*binding))
+                }
+            }
+            SettingsButton::StickDeadzone => {
+                format!("Stick Deadzone: {:.2}", settings.stick_deadzone)
+            }
+            SettingsButton::Difficulty => format!("Difficulty: {}", settings.difficulty.name()),
+            SettingsButton::Back => "Back".to_string(),
+        }
+    }
+}
+
+fn setup_settings_menu(
+    mut commands: Commands,
+    ui_assets: Res<UIAssets>,
+    settings: Res<Settings>,
+    rebinding: Res<Rebinding>,
+) {
+    let buttons = [
+        SettingsButton::MasterVolume,
+        SettingsButton::MusicVolume,
+        SettingsButton::SfxVolume,
+        SettingsButton::WindowMode,
+        SettingsButton::Resolution,
+        SettingsButton::Bind(Binding::Up),
+        SettingsButton::Bind(Binding::Down),
+        SettingsButton::Bind(Binding::Left),
+        SettingsButton::Bind(Binding::Right),
+        SettingsButton::Bind(Binding::Jump),
+        SettingsButton::Bind(Binding::Crouch),
+        SettingsButton::StickDeadzone,
+        SettingsButton::Difficulty,
+        SettingsButton::Back,
+    ];
+
+    buttons
+        .into_iter()
+        .fold(MenuBuilder::new(), |menu, action| {
+            menu.button(action.label(&settings, &rebinding), action)
+        })
+        .backdrop(Color::rgba(0.0, 0.0, 0.0, 0.8))
+        .button_size(Size::new(Val::Px(420.0), Val::Px(40.0)))
+        .font_size(26.0)
+        .spawn(&mut commands, &ui_assets)
+        .insert(SettingsMenu);
+}
+
+fn handle_settings_buttons(
+    mut selected: EventReader<MenuSelected<SettingsButton>>,
+    mut settings: ResMut<Settings>,
+    mut rebinding: ResMut<Rebinding>,
+    mut state: ResMut<State<GameState>>,
+) {
+    for MenuSelected(action) in selected.iter() {
+        //Picking anything else gives up on a binding that's waiting for a key
+        rebinding.0 = None;
+        match action {
+            SettingsButton::MasterVolume => {
+                settings.master_volume = next_volume(settings.master_volume)
+            }
+            SettingsButton::MusicVolume => {
+                settings.music_volume = next_volume(settings.music_volume)
+            }
+            SettingsButton::SfxVolume => settings.sfx_volume = next_volume(settings.sfx_volume),
+            SettingsButton::WindowMode => settings.window_mode = settings.window_mode.next(),
+            SettingsButton::Resolution => {
+                settings.resolution = next_resolution(settings.resolution)
+            }
+            SettingsButton::Bind(binding) => rebinding.0 = Some(*binding),
+            SettingsButton::StickDeadzone => {
+                settings.stick_deadzone = next_deadzone(settings.stick_deadzone)
+            }
+            SettingsButton::Difficulty => settings.difficulty = settings.difficulty.next(),
+            SettingsButton::Back => {
+                let _ = state.pop();
+            }
+        }
+    }
+}
+
+//Escape cancels a rebind in progress, otherwise it backs out of the screen
+fn settings_keyboard_input(
+    mut keys: ResMut<Input<KeyCode>>,
+    mut settings: ResMut<Settings>,
+    mut rebinding: ResMut<Rebinding>,
+    mut state: ResMut<State<GameState>>,
+) {
+    match rebinding.0 {
+        Some(binding) => {
+            let pressed = keys.get_just_pressed().next().copied();
+            if let Some(key) = pressed {
+                keys.clear_just_pressed(key);
+                if key != KeyCode::Escape {
+                    settings.bindings.set(binding, key);
+                }
+                rebinding.0 = None;
+            }
+        }
+        None => {
+            if keys.clear_just_pressed(KeyCode::Escape) {
+                let _ = state.pop();
+            }
+        }
+    }
+}
+
+fn update_setting_text(
+    settings: Res<Settings>,
+    rebinding: Res<Rebinding>,
+    mut query: Query<(&mut Text, &MenuLabel<SettingsButton>)>,
+) {
+    if !settings.is_changed() && !rebinding.is_changed() {
+        return;
+    }
+    for (mut text, setting) in query.iter_mut() {
+        text.sections[0].value = setting.0.label(&settings, &rebinding);
+    }
+}
+
+fn apply_window_settings(settings: Res<Settings>, mut windows: ResMut<Windows>) {
+    if !settings.is_changed() {
+        return;
+    }
+    let window = windows.get_primary_mut().unwrap();
+    let mode = settings.window_mode.window_mode();
+    if window.mode() != mode {
+        window.set_mode(mode);
+    }
+    window.set_resolution(settings.resolution.0, settings.resolution.1);
+}
+
+//A replay keeps the size it was recorded at, resizing while it plays only changes what's put back after
+fn track_window_size(
+    mut resize_events: EventReader<WindowResized>,
+    mut win_size: ResMut<WindowSize>,
+    mut playback: ResMut<ReplayPlayback>,
+) {
+    for event in resize_events.iter() {
+        if !event.id.is_primary() {
+            continue;
+        }
+        match playback.viewer_window_mut() {
+            Some(window) => *window = (event.width, event.height),
+            None => {
+                win_size.w = event.width;
+                win_size.h = event.height;
+            }
+        }
+    }
+}
+
+fn despawn_settings_menu(
+    mut commands: Commands,
+    query: Query<Entity, With<SettingsMenu>>,
+    mut rebinding: ResMut<Rebinding>,
+) {
+    rebinding.0 = None;
+    for entity in query.iter() {
+        commands.entity(entity).despawn_recursive();
+    }
+}
+
+fn save_settings(settings: Res<Settings>) {
+    if let Err(err) = settings.save() {
+        eprintln!("Couldn't save settings: {}", err);
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    //A fresh directory per test, so tests running side by side don't trip over each other's files
+    fn scratch_path(name: &str) -> PathBuf {
+        let dir = std::env::temp_dir().join("mbrh_settings").join(name);
+        let _ = fs::remove_dir_all(&dir);
+        fs::create_dir_all(&dir).unwrap();
+        dir.join("settings.ron")
+    }
+
+    #[test]
+    fn saved_settings_load_back() {
+        let path = scratch_path("round_trip");
+        let mut settings = Settings::load(path.clone());
+        settings.difficulty = Difficulty::Hard;
+        settings.resolution = RESOLUTIONS[2];
+        settings.bindings.jump = KeyCode::J;
+        settings.save().unwrap();
+        assert!(!path.with_extension("ron.tmp").exists());
+
+        let loaded = Settings::load(path);
+        assert_eq!(loaded.difficulty, Difficulty::Hard);
+        assert_eq!(loaded.resolution, RESOLUTIONS[2]);
+        assert_eq!(loaded.bindings.jump, KeyCode::J);
+    }
+
+    #[test]
+    fn missing_fields_fall_back_to_their_defaults() {
+        let path = scratch_path("partial");
+        fs::write(&path, "(difficulty: Easy, sfx_volume: 0.5)").unwrap();
+        let settings = Settings::load(path);
+        let defaults = Settings::default();
+        assert_eq!(settings.difficulty, Difficulty::Easy);
+        assert_eq!(settings.sfx_volume, 0.5);
+        assert_eq!(settings.music_volume, defaults.music_volume);
+        assert_eq!(settings.stick_deadzone, defaults.stick_deadzone);
+        assert_eq!(settings.bindings.up, defaults.bindings.up);
+    }
+
+    //Settings that have since been renamed or dropped, like the old aim sensitivity
+    #[test]
+    fn unknown_fields_are_ignored() {
+        let path = scratch_path("unknown");
+        fs::write(&path, "(aim_sensitivity: 1.5, difficulty: Hard)").unwrap();
+        let settings = Settings::load(path);
+        assert_eq!(settings.difficulty, Difficulty::Hard);
+    }
+
+    #[test]
+    fn broken_file_falls_back_to_the_defaults() {
+        let path = scratch_path("broken");
+        fs::write(&path, "(difficulty: Impossible").unwrap();
+        let settings = Settings::load(path.clone());
+        assert_eq!(settings.difficulty, Difficulty::Normal);
+        //And saving still goes to the same file
+        settings.save().unwrap();
+        assert_eq!(Settings::load(path).difficulty, Difficulty::Normal);
+    }
+
+    #[test]
+    fn stick_deadzone_wraps_around() {
+        let mut deadzone = MIN_STICK_DEADZONE;
+        let mut steps = 0;
+        loop {
+            deadzone = next_deadzone(deadzone);
+            steps += 1;
+            if deadzone == MIN_STICK_DEADZONE {
+                break;
+            }
+            assert!(deadzone <= MAX_STICK_DEADZONE + STICK_DEADZONE_STEP / 2.);
+        }
+        assert_eq!(steps, 10);
+    }
+}
@@ -0,0 +1,204 @@
+use std::collections::HashMap;
+use std::fs;
+
+use bevy::prelude::*;
+use rand::Rng;
+use serde::Deserialize;
+
+use crate::blaster::{BlasterFiredEvent, OverheatEvent};
+use crate::civilian::CivilianRescuedEvent;
+use crate::components::Player;
+use crate::projectile_collision::{EnemyKilledEvent, LivingBeingHitEvent};
+use crate::settings::Settings;
+use crate::states::GameState;
+
+//Sound effects are described in assets/sounds.ron and played with a SoundEvent by name. Gameplay events
+//are turned into sounds here so the systems sending them don't need to know anything about audio
+pub struct SoundPlugin;
+
+impl Plugin for SoundPlugin {
+    fn build(&self, app: &mut App) {
+        app.add_event::<SoundEvent>()
+            .insert_resource(PlayingSounds(HashMap::new()))
+            .add_startup_system(load_sounds)
+            .add_system_set(
+                SystemSet::on_update(GameState::MainGame)
+                    .with_system(blaster_sounds_system)
+                    .with_system(hit_sounds_system)
+                    .with_system(death_sounds_system)
+                    .with_system(rescue_sounds_system),
+            )
+            .add_system_to_stage(CoreStage::PostUpdate, play_sounds_system);
+    }
+}
+
+const SOUNDS_PATH: &str = "assets/sounds.ron";
+
+pub struct SoundEvent {
+    pub name: String,
+}
+
+impl SoundEvent {
+    pub fn new(name: &str) -> Self {
+        Self {
+            name: name.to_string(),
+        }
+    }
+}
+
+fn default_volume() -> f32 {
+    1.0
+}
+
+fn default_max_instances() -> usize {
+    4
+}
+
+#[derive(Deserialize)]
+struct SoundDescriptor {
+    files: Vec<String>,
+    #[serde(default = "default_volume")]
+    volume: f32,
+    #[serde(default)]
+    pitch_variation: f32,
+    #[serde(default = "default_max_instances")]
+    max_instances: usize,
+    seconds: f32,
+}
+
+struct Sound {
+    variations: Vec<Handle<AudioSource>>,
+    volume: f32,
+    pitch_variation: f32,
+    max_instances: usize,
+    seconds: f32,
+}
+
+struct Sounds(HashMap<String, Sound>);
+
+//When each copy of a sound still playing is due to finish, by sound name
+struct PlayingSounds(HashMap<String, Vec<f64>>);
+
+//A broken sounds file is a bug in the assets, same as a broken effects file
+fn load_sounds(mut commands: Commands, asset_server: Res<AssetServer>) {
+    let contents = fs::read_to_string(SOUNDS_PATH)
+        .unwrap_or_else(|err| panic!("Couldn't read {}: {}", SOUNDS_PATH, err));
+    let descriptors: HashMap<String, SoundDescriptor> = ron::from_str(&contents)
+        .unwrap_or_else(|err| panic!("Couldn't load {}: {}", SOUNDS_PATH, err));
+
+    let sounds = descriptors
+        .into_iter()
+        .map(|(name, descriptor)| {
+            if descriptor.files.is_empty() {
+                panic!("{} has no files for {}", SOUNDS_PATH, name);
+            }
+            let sound = Sound {
+                variations: descriptor
+                    .files
+                    .iter()
+                    .map(|file| asset_server.load(file.as_str()))
+                    .collect(),
+                volume: descriptor.volume,
+                pitch_variation: descriptor.pitch_variation,
+                max_instances: descriptor.max_instances,
+                seconds: descriptor.seconds,
+            };
+            (name, sound)
+        })
+        .collect();
+    commands.insert_resource(Sounds(sounds));
+}
+
+fn blaster_sounds_system(
+    mut fired_events: EventReader<BlasterFiredEvent>,
+    mut overheat_events: EventReader<OverheatEvent>,
+    mut send_sound: EventWriter<SoundEvent>,
+) {
+    for event in fired_events.iter() {
+        let name = if !event.from_player {
+            "enemy_fire"
+        } else if event.power_shot {
+            "power_shot"
+        } else {
+            "fire"
+        };
+        send_sound.send(SoundEvent::new(name));
+    }
+    for _ in overheat_events.iter() {
+        send_sound.send(SoundEvent::new("overheat"));
+    }
+}
+
+fn hit_sounds_system(
+    mut hit_events: EventReader<LivingBeingHitEvent>,
+    mut send_sound: EventWriter<SoundEvent>,
+    player_query: Query<(), With<Player>>,
+) {
+    for event in hit_events.iter() {
+        if player_query.get(event.entity).is_ok() {
+            send_sound.send(SoundEvent::new("player_hit"));
+        } else {
+            send_sound.send(SoundEvent::new("hit"));
+        }
+    }
+}
+
+fn death_sounds_system(
+    mut killed_events: EventReader<EnemyKilledEvent>,
+    mut send_sound: EventWriter<SoundEvent>,
+) {
+    for _ in killed_events.iter() {
+        send_sound.send(SoundEvent::new("enemy_death"));
+    }
+}
+
+fn rescue_sounds_system(
+    mut rescued_events: EventReader<CivilianRescuedEvent>,
+    mut send_sound: EventWriter<SoundEvent>,
+) {
+    for _ in rescued_events.iter() {
+        send_sound.send(SoundEvent::new("civilian_rescued"));
+    }
+}
+
+//Volume is picked up from the settings as each sound starts, they're short enough that a change in the
+//settings menu doesn't need to reach the ones already playing
+fn play_sounds_system(
+    time: Res<Time>,
+    audio: Res<Audio>,
+    settings: Res<Settings>,
+    sounds: Res<Sounds>,
+    mut playing: ResMut<PlayingSounds>,
+    mut sound_events: EventReader<SoundEvent>,
+) {
+    let now = time.seconds_since_startup();
+    for copies in playing.0.values_mut() {
+        copies.retain(|finish| *finish > now);
+    }
+
+    let volume = settings.sfx_volume();
+    let mut rng = rand::thread_rng();
+    for event in sound_events.iter() {
+        let sound = match sounds.0.get(&event.name) {
+            Some(sound) => sound,
+            None => {
+                warn!("No sound called {} in {}", event.name, SOUNDS_PATH);
+                continue;
+            }
+        };
+        let copies = playing.0.entry(event.name.clone()).or_default();
+        if copies.len() >= sound.max_instances || volume <= 0.0 {
+            continue;
+        }
+
+        let variation = &sound.variations[rng.gen_range(0..sound.variations.len())];
+        let speed = 1.0 + rng.gen_range(-1.0..=1.0) * sound.pitch_variation;
+        audio.play_with_settings(
+            variation.clone(),
+            PlaybackSettings::ONCE
+                .with_volume(sound.volume * volume)
+                .with_speed(speed),
+        );
+        copies.push(now + (sound.seconds / speed) as f64);
+    }
+}
@@ -1,77 +1,88 @@
+use bevy::ecs::system::SystemParam;
 use bevy::prelude::*;
 use rand::Rng;
 use std::collections::VecDeque;
 
 use crate::civilian::spawn_civilian;
 use crate::components::{Civilian, Enemy};
-use crate::enemy::spawn_crab;
-use crate::resources::{GameTextures, SpawnQueue, SpawnType, WindowSize};
-use crate::rounds::{PopulateQueueEvent, RoundTracker};
+use crate::enemy::spawn_enemy;
+use crate::fixed_step::{FixedStepApp, FixedSystems};
+use crate::graphics::SpriteSheets;
+use crate::resources::{GameRng, SpawnQueue, SpawnType, WindowSize};
+use crate::rounds::RoundTracker;
 use crate::states::GameState;
 
-pub struct NewRoundEvent {}
+pub struct NewRoundEvent {
+    pub round: u32,
+}
 
 pub struct SpawnManagerPlugin;
 
 impl Plugin for SpawnManagerPlugin {
     fn build(&self, app: &mut App) {
-        app.add_event::<PopulateQueueEvent>().add_system_set(
-            SystemSet::on_update(GameState::MainGame).with_system(spawn_manager_system),
+        app.add_fixed_systems(
+            FixedSystems::on_update(GameState::MainGame).with_system(spawn_manager_system),
         );
     }
 }
 
+//Random spots on the field, drawn from the run's rng
+#[derive(SystemParam)]
+struct SpawnPositions<'w, 's> {
+    win_size: Res<'w, WindowSize>,
+    rng: ResMut<'w, GameRng>,
+    #[system_param(ignore)]
+    _marker: std::marker::PhantomData<&'s ()>,
+}
+
+impl SpawnPositions<'_, '_> {
+    fn next(&mut self) -> Vec2 {
+        let (w, h) = (self.win_size.w, self.win_size.h);
+        Vec2::new(
+            self.rng.gen_range(-w / 2.0..w / 2.0),
+            self.rng.gen_range(-h / 2.0..h / 2.0),
+        )
+    }
+}
+
 fn spawn_manager_system(
     mut cmds: Commands,
     mut round_tracker: ResMut<RoundTracker>,
     mut spawn_queue: ResMut<SpawnQueue>,
-    win_size: Res<WindowSize>,
-    game_textures: Res<GameTextures>,
+    mut spawn_positions: SpawnPositions,
+    sprite_sheets: Res<SpriteSheets>,
     query: Query<(), Or<(With<Civilian>, With<Enemy>)>>,
     mut state: ResMut<State<GameState>>,
-
-    asset_server: Res<AssetServer>,
-    mut texture_atlases: ResMut<Assets<TextureAtlas>>,
-    mut send_populate_queue: EventWriter<PopulateQueueEvent>,
 ) {
     let current_max_spawns = round_tracker.current_round_data().unwrap().max_spawns as usize;
     let number_of_spawns = query.iter().count();
 
-    if spawn_queue.len() == 0 && number_of_spawns == 0 {
-        if (!round_tracker.next_round()) {
-            state.push(GameState::GameOver).unwrap();
+    //The next round is started by the intermission screen once the player is ready. The player's last
+    //life can run out on the same tick, in which case whichever transition was queued first wins
+    if spawn_queue.is_empty() && number_of_spawns == 0 {
+        if round_tracker.is_final_round() {
+            let _ = state.push(GameState::GameOver);
+        } else {
+            let _ = state.push(GameState::Intermission);
         }
-        send_populate_queue.send(PopulateQueueEvent {});
+        return;
     }
 
-    let texture_handle_crab =
-        asset_server.load("darians-assets/TeamGunner/CHARACTER_SPRITES/Red/Red_Soldier.png");
-    let texture_atlas_crab =
-        TextureAtlas::from_grid(texture_handle_crab, Vec2::new(50.0, 50.0), 8, 5);
-    let texture_atlas_handle_crab = texture_atlases.add(texture_atlas_crab);
-
-    let texture_handle_civ =
-        asset_server.load("darians-assets/TeamGunner/CHARACTER_SPRITES/Green/Green_Soldier.png");
-    let texture_atlas_civ =
-        TextureAtlas::from_grid(texture_handle_civ, Vec2::new(50.0, 50.0), 8, 5);
-    let texture_atlas_handle_civ = texture_atlases.add(texture_atlas_civ);
-
     if current_max_spawns > number_of_spawns {
-        let mut rng = rand::thread_rng();
         let diff = current_max_spawns - number_of_spawns;
         for _ in 0..diff {
-            let spawn_position = Vec2::new(
-                rng.gen_range(-win_size.w / 2.0..win_size.w / 2.0),
-                rng.gen_range(-win_size.h / 2.0..win_size.h / 2.0),
-            );
+            let spawn_position = spawn_positions.next();
             match spawn_queue.pop_front() {
                 Some(SpawnType::Civilian) => {
-                    spawn_civilian(&mut cmds, spawn_position, &texture_atlas_handle_civ)
-                }
-                Some(SpawnType::Crab) => {
-                    spawn_crab(&mut cmds, spawn_position, &texture_atlas_handle_crab)
+                    spawn_civilian(&mut cmds, spawn_position, sprite_sheets.get("civilian"))
                 }
-                _ => {}
+                Some(archetype) => spawn_enemy(
+                    &mut cmds,
+                    archetype,
+                    spawn_position,
+                    sprite_sheets.get("crab"),
+                ),
+                None => {}
             }
         }
     }
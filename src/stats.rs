@@ -0,0 +1,249 @@
+use std::error::Error;
+use std::fs::File;
+use std::path::Path;
+
+use bevy::prelude::*;
+use serde::Serialize;
+
+use crate::blaster::{BlasterFiredEvent, OverheatEvent};
+use crate::civilian::{CivilianLostEvent, CivilianRescuedEvent};
+use crate::components::Enemy;
+use crate::debug;
+use crate::fixed_step::{FixedStepApp, FixedSystems, FixedTime};
+use crate::pause::RestartRoundEvent;
+use crate::player::LifeLostEvent;
+use crate::projectile_collision::{EnemyKilledEvent, LivingBeingHitEvent};
+use crate::resources::{GameMode, GameRng, PlayerScore};
+use crate::spawn_manager::NewRoundEvent;
+use crate::states::GameState;
+
+pub struct StatsPlugin;
+
+#[derive(SystemLabel, Debug, Clone, PartialEq, Eq, Hash)]
+pub struct FinishRoundLabel;
+
+impl Plugin for StatsPlugin {
+    fn build(&self, app: &mut App) {
+        app.insert_resource(RoundStats::default())
+            .insert_resource(RunStats::default())
+            .add_system_set(SystemSet::on_enter(GameState::MainGame).with_system(start_run_system))
+            .add_fixed_systems(
+                FixedSystems::on_update(GameState::MainGame)
+                    .with_system(reset_round_stats_system)
+                    .with_system(restart_round_stats_system)
+                    .with_system(track_shots_system)
+                    .with_system(track_kills_system)
+                    .with_system(track_civilians_system)
+                    .with_system(track_player_system)
+                    .with_system(track_round_time_system),
+            )
+            .add_system_set(
+                SystemSet::on_enter(GameState::Intermission)
+                    .with_system(finish_round_system.label(FinishRoundLabel)),
+            )
+            .add_system_set(
+                SystemSet::on_enter(GameState::GameOver)
+                    .with_system(finish_round_system.label(FinishRoundLabel))
+                    .with_system(export_run_stats_system.after(FinishRoundLabel)),
+            );
+    }
+}
+
+#[derive(Clone, Default, Serialize)]
+pub struct RoundStats {
+    pub round: u32,
+    pub kills: u32,
+    pub power_shot_kills: u32,
+    pub civilians_saved: u32,
+    pub civilians_lost: u32,
+    pub shots_fired: u32,
+    pub shots_hit: u32,
+    pub overheats: u32,
+    pub lives_lost: u32,
+    pub seconds: f32,
+    pub score_at_start: usize,
+    pub score_gained: usize,
+}
+
+impl RoundStats {
+    pub fn accuracy(&self) -> f32 {
+        accuracy(self.shots_hit, self.shots_fired)
+    }
+
+    pub fn score_gained(&self, score: &PlayerScore) -> usize {
+        score.0.saturating_sub(self.score_at_start)
+    }
+}
+
+fn accuracy(hit: u32, fired: u32) -> f32 {
+    if fired == 0 {
+        0.
+    } else {
+        hit as f32 / fired as f32
+    }
+}
+
+#[derive(Serialize)]
+pub struct RunStats {
+    pub mode: GameMode,
+    pub seed: u64,
+    pub final_score: usize,
+    pub rounds: Vec<RoundStats>,
+}
+
+impl Default for RunStats {
+    fn default() -> Self {
+        Self {
+            mode: GameMode::Classic,
+            seed: 0,
+            final_score: 0,
+            rounds: Vec::new(),
+        }
+    }
+}
+
+impl RunStats {
+    pub fn total(&self, stat: impl Fn(&RoundStats) -> u32) -> u32 {
+        self.rounds.iter().map(stat).sum()
+    }
+
+    pub fn accuracy(&self) -> f32 {
+        accuracy(
+            self.total(|round| round.shots_hit),
+            self.total(|round| round.shots_fired),
+        )
+    }
+
+    pub fn seconds(&self) -> f32 {
+        self.rounds.iter().map(|round| round.seconds).sum()
+    }
+
+    pub fn export(&self, path: &Path) -> Result<(), Box<dyn Error>> {
+        if path.extension().is_some_and(|extension| extension == "csv") {
+            let mut writer = csv::Writer::from_path(path)?;
+            for round in self.rounds.iter() {
+                writer.serialize(round)?;
+            }
+            writer.flush()?;
+        } else {
+            serde_json::to_writer_pretty(File::create(path)?, self)?;
+        }
+        Ok(())
+    }
+}
+
+fn start_run_system(
+    mut run_stats: ResMut<RunStats>,
+    game_mode: Res<GameMode>,
+    game_rng: Res<GameRng>,
+) {
+    info!(
+        "Starting a {} run with seed {}",
+        game_mode.name(),
+        game_rng.seed()
+    );
+    *run_stats = RunStats {
+        mode: *game_mode,
+        seed: game_rng.seed(),
+        ..Default::default()
+    };
+}
+
+fn reset_round_stats_system(
+    mut new_round_events: EventReader<NewRoundEvent>,
+    mut stats: ResMut<RoundStats>,
+    score: Res<PlayerScore>,
+) {
+    for event in new_round_events.iter() {
+        *stats = RoundStats {
+            round: event.round,
+            score_at_start: score.0,
+            ..Default::default()
+        };
+    }
+}
+
+fn restart_round_stats_system(
+    mut restart_events: EventReader<RestartRoundEvent>,
+    mut stats: ResMut<RoundStats>,
+) {
+    if restart_events.iter().last().is_some() {
+        *stats = RoundStats {
+            round: stats.round,
+            score_at_start: stats.score_at_start,
+            ..Default::default()
+        };
+    }
+}
+
+fn track_shots_system(
+    mut fired_events: EventReader<BlasterFiredEvent>,
+    mut hit_events: EventReader<LivingBeingHitEvent>,
+    mut overheat_events: EventReader<OverheatEvent>,
+    enemy_query: Query<(), With<Enemy>>,
+    mut stats: ResMut<RoundStats>,
+) {
+    for event in fired_events.iter() {
+        if event.from_player {
+            stats.shots_fired += 1;
+        }
+    }
+    //Only the player's blaster can hit enemies
+    for event in hit_events.iter() {
+        if enemy_query.contains(event.entity) {
+            stats.shots_hit += 1;
+        }
+    }
+    stats.overheats += overheat_events.iter().count() as u32;
+}
+
+fn track_kills_system(
+    mut kill_events: EventReader<EnemyKilledEvent>,
+    mut stats: ResMut<RoundStats>,
+) {
+    for event in kill_events.iter() {
+        stats.kills += 1;
+        if event.power_shot {
+            stats.power_shot_kills += 1;
+        }
+    }
+}
+
+fn track_civilians_system(
+    mut rescued_events: EventReader<CivilianRescuedEvent>,
+    mut lost_events: EventReader<CivilianLostEvent>,
+    mut stats: ResMut<RoundStats>,
+) {
+    stats.civilians_saved += rescued_events.iter().count() as u32;
+    stats.civilians_lost += lost_events.iter().count() as u32;
+}
+
+fn track_player_system(
+    mut life_lost_events: EventReader<LifeLostEvent>,
+    mut stats: ResMut<RoundStats>,
+) {
+    stats.lives_lost += life_lost_events.iter().count() as u32;
+}
+
+fn track_round_time_system(time: Res<FixedTime>, mut stats: ResMut<RoundStats>) {
+    stats.seconds += time.delta_seconds();
+}
+
+fn finish_round_system(
+    mut stats: ResMut<RoundStats>,
+    mut run_stats: ResMut<RunStats>,
+    score: Res<PlayerScore>,
+) {
+    stats.score_gained = stats.score_gained(&score);
+    run_stats.rounds.push(stats.clone());
+    run_stats.final_score = score.0;
+}
+
+fn export_run_stats_system(run_stats: Res<RunStats>) {
+    if let Some(path) = debug::stats_export_path() {
+        match run_stats.export(&path) {
+            Ok(()) => info!("Run stats written to {}", path.display()),
+            Err(err) => warn!("Failed to write run stats to {}: {}", path.display(), err),
+        }
+    }
+}
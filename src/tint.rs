@@ -0,0 +1,97 @@
+use bevy::prelude::*;
+
+use crate::components::{Health, LivingBeing, Player};
+use crate::constants::{
+    HIT_FLASH_BRIGHTNESS, HIT_FLASH_SECONDS, LOW_HEALTH_FRACTION, MAX_BLASTER_HEAT,
+};
+use crate::projectile_collision::LivingBeingHitEvent;
+use crate::resources::BlasterHeat;
+use crate::states::GameState;
+
+//Colour states on top of whatever frame is animating: a bright flash when hit that fades straight back,
+//a pulsing red tint while low on health and an orange glow on the player as the blaster heats up
+pub struct TintPlugin;
+
+impl Plugin for TintPlugin {
+    fn build(&self, app: &mut App) {
+        app.add_system(attach_tints_system).add_system_set(
+            SystemSet::on_update(GameState::MainGame)
+                .with_system(hit_flash_system)
+                .with_system(tint_system),
+        );
+    }
+}
+
+const LOW_HEALTH_COLOR: Color = Color::rgb(1.0, 0.35, 0.35);
+const HEAT_GLOW_COLOR: Color = Color::rgb(1.0, 0.55, 0.15);
+
+#[derive(Component)]
+pub struct Tint {
+    //Seconds left on the hit flash
+    flash: f32,
+    //Health when spawned, to tell how hurt it is now
+    max_health: u32,
+}
+
+fn attach_tints_system(mut cmds: Commands, query: Query<(Entity, &Health), Added<LivingBeing>>) {
+    for (entity, health) in query.iter() {
+        cmds.entity(entity).insert(Tint {
+            flash: 0.0,
+            max_health: health.health,
+        });
+    }
+}
+
+fn hit_flash_system(mut hit_events: EventReader<LivingBeingHitEvent>, mut query: Query<&mut Tint>) {
+    for event in hit_events.iter() {
+        if let Ok(mut tint) = query.get_mut(event.entity) {
+            tint.flash = HIT_FLASH_SECONDS;
+        }
+    }
+}
+
+fn mix(from: Color, to: Color, t: f32) -> Color {
+    Color::rgba(
+        from.r() + (to.r() - from.r()) * t,
+        from.g() + (to.g() - from.g()) * t,
+        from.b() + (to.b() - from.b()) * t,
+        from.a() + (to.a() - from.a()) * t,
+    )
+}
+
+fn tint_system(
+    time: Res<Time>,
+    blaster_heat: Res<BlasterHeat>,
+    mut query: Query<(&mut Tint, &Health, &mut TextureAtlasSprite, Option<&Player>)>,
+) {
+    let pulse = 0.5 + 0.5 * (time.seconds_since_startup() as f32 * 6.0).sin();
+    for (mut tint, health, mut sprite, player) in query.iter_mut() {
+        tint.flash = (tint.flash - time.delta_seconds()).max(0.0);
+
+        let mut color = Color::WHITE;
+        if player.is_some() {
+            let heat = (blaster_heat.value / MAX_BLASTER_HEAT).clamp(0.0, 1.0);
+            let overheated = !blaster_heat.overheat_cooldown_timer.ready();
+            let glow = if overheated {
+                0.5 + 0.3 * pulse
+            } else {
+                heat * 0.7
+            };
+            color = mix(color, HEAT_GLOW_COLOR, glow);
+        }
+        let low_health = tint.max_health > 1
+            && (health.health as f32) <= tint.max_health as f32 * LOW_HEALTH_FRACTION;
+        if low_health {
+            color = mix(color, LOW_HEALTH_COLOR, 0.4 + 0.6 * pulse);
+        }
+
+        //Sprite colour multiplies the texture, so going past 1.0 is what washes it out towards white
+        let flash = 1.0 + (HIT_FLASH_BRIGHTNESS - 1.0) * (tint.flash / HIT_FLASH_SECONDS);
+        sprite.color = Color::rgba(
+            color.r() * flash,
+            color.g() * flash,
+            color.b() * flash,
+            color.a(),
+        );
+    }
+}
@@ -1,8 +1,9 @@
 use bevy::prelude::*;
 
-use crate::components::{PlayerLivesUI, RoundUI, ScoreUi};
-use crate::resources::{GameFont, PlayerLives, PlayerScore};
+use crate::components::{ComboUi, GameUi, HudText, RoundUI, ScoreUi};
+use crate::resources::{GameFont, PlayerScore};
 use crate::rounds::RoundTracker;
+use crate::scoring::Combo;
 use crate::states::GameState;
 
 pub struct UiPlugin;
@@ -13,8 +14,8 @@ impl Plugin for UiPlugin {
             .add_system_set(
                 SystemSet::on_update(GameState::MainGame)
                     .with_system(update_score_system)
-                    .with_system(update_lives_system)
-                    .with_system(update_round_system),
+                    .with_system(update_round_system)
+                    .with_system(update_combo_system),
             )
             .add_system_set(
                 SystemSet::on_update(GameState::GameOver).with_system(update_score_system),
@@ -34,47 +35,52 @@ fn spawn_ui_system(mut cmds: Commands, score: Res<PlayerScore>, font: Res<GameFo
             ..default()
         }),
     )
-    .insert(ScoreUi);
+    .insert(ScoreUi)
+    .insert(HudText { base_size: 40.0 })
+    .insert(GameUi);
 
     cmds.spawn_bundle(
         TextBundle::from_sections([TextSection::from_style(TextStyle {
             font: font.0.clone(),
             font_size: 40.0,
-            color: Color::rgb(0.0, 1.0, 0.0),
+            color: Color::rgb(1.0, 0.0, 1.0),
         })])
         .with_style(Style {
             align_self: AlignSelf::FlexStart,
             position_type: PositionType::Absolute,
             position: UiRect {
-                left: Val::Px(0.0),
-                right: Val::Px(0.0),
+                left: Val::Percent(85.0),
+                right: Val::Percent(0.0),
                 top: Val::Percent(80.0),
                 bottom: Val::Percent(0.0),
             },
             ..default()
         }),
     )
-    .insert(PlayerLivesUI);
+    .insert(RoundUI)
+    .insert(HudText { base_size: 40.0 })
+    .insert(GameUi);
 
     cmds.spawn_bundle(
         TextBundle::from_sections([TextSection::from_style(TextStyle {
             font: font.0.clone(),
             font_size: 40.0,
-            color: Color::rgb(1.0, 0.0, 1.0),
+            color: Color::rgb(1.0, 0.8, 0.0),
         })])
         .with_style(Style {
             align_self: AlignSelf::FlexStart,
             position_type: PositionType::Absolute,
             position: UiRect {
-                left: Val::Percent(85.0),
-                right: Val::Percent(0.0),
-                top: Val::Percent(80.0),
-                bottom: Val::Percent(0.0),
+                left: Val::Px(0.0),
+                top: Val::Px(45.0),
+                ..default()
             },
             ..default()
         }),
     )
-    .insert(RoundUI);
+    .insert(ComboUi)
+    .insert(HudText { base_size: 40.0 })
+    .insert(GameUi);
 }
 
 fn update_score_system(score: Res<PlayerScore>, mut query: Query<&mut Text, With<ScoreUi>>) {
@@ -82,12 +88,20 @@ fn update_score_system(score: Res<PlayerScore>, mut query: Query<&mut Text, With
     score_text.sections[0].value = format!("Scorrrre: {}", score.0);
 }
 
-fn update_lives_system(lives: Res<PlayerLives>, mut query: Query<&mut Text, With<PlayerLivesUI>>) {
-    let mut lives_text = query.get_single_mut().unwrap();
-    lives_text.sections[0].value = format!("Lives: {}", lives.0);
-}
-
 fn update_round_system(round: Res<RoundTracker>, mut query: Query<&mut Text, With<RoundUI>>) {
     let mut round_text = query.get_single_mut().unwrap();
-    round_text.sections[0].value = format!("Round: {}", round.current_round.unwrap());
+    if round.is_endless() {
+        round_text.sections[0].value = format!("Wave: {}", round.current_round.unwrap());
+    } else {
+        round_text.sections[0].value = format!("Round: {}", round.current_round.unwrap());
+    }
+}
+
+fn update_combo_system(combo: Res<Combo>, mut query: Query<&mut Text, With<ComboUi>>) {
+    let mut combo_text = query.get_single_mut().unwrap();
+    if combo.multiplier > 1 {
+        combo_text.sections[0].value = format!("x{}", combo.multiplier);
+    } else {
+        combo_text.sections[0].value.clear();
+    }
 }
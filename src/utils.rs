@@ -23,6 +23,11 @@ impl CooldownTimer {
         self.elapsed.is_none()
     }
 
+    pub fn remaining(&self) -> Option<Duration> {
+        self.elapsed
+            .map(|elapsed| self.duration.saturating_sub(elapsed))
+    }
+
     pub fn tick(&mut self, delta: Duration) {
         if let Some(ref mut elapsed) = self.elapsed {
             *elapsed += delta;